@@ -0,0 +1,78 @@
+// Times tile collision queries for lots of projectiles at once, against checking every tile like it used to.
+// Segments are the paths of fast moving things, as long as a bullet goes in a few ticks
+//   cargo run --release --example collision_bench --no-default-features
+
+use std::{hint::black_box, time::Instant};
+
+use breakout::game::{level_pack::LevelPack, world::{bullet::Bullet, level::{Level, Tile}, rng::Rng, rules::GameRules}};
+use macroquad::math::{vec2, Rect, Vec2};
+
+const TICKS: usize = 1000;
+
+fn main() {
+    let level_pack = LevelPack::load_from_file(include_bytes!("../ALBUMS I LIKE.brk").to_vec()).expect("the level pack should be valid");
+    let level = &level_pack.levels()[0];
+    let view_size = Level::view_size();
+    let rules = GameRules::default();
+
+    println!("{:>6} {:>14} {:>14} {:>14} {:>14}", "COUNT", "FULL SCAN", "RECT QUERY", "SEGMENT QUERY", "BULLETS");
+    for count in [10, 100, 500, 1000] {
+        let mut rng = Rng::new(count as u64);
+        let rects: Vec<Rect> = (0..count)
+            .map(|_| Rect::new(rng.gen_range(0.0, view_size.x), rng.gen_range(0.0, view_size.y), 4.0, 4.0))
+            .collect();
+
+        // The old way, looking at every tile for every projectile
+        let start = Instant::now();
+        for _ in 0..TICKS {
+            for r in &rects {
+                for (i, t) in level.tiles().iter().enumerate() {
+                    if *t != Tile::Air && level.tile_rect(i).overlaps(r) {
+                        black_box(i);
+                    }
+                }
+            }
+        }
+        let full_scan = start.elapsed();
+
+        let start = Instant::now();
+        for _ in 0..TICKS {
+            for r in &rects {
+                for i in level.tiles_in_rect(*r) {
+                    black_box(i);
+                }
+            }
+        }
+        let rect_query = start.elapsed();
+
+        let segments: Vec<(Vec2, Vec2)> = rects.iter()
+            .map(|r| (r.point(), r.point() + Vec2::from_angle(rng.gen_range(0.0, std::f32::consts::TAU)) * 10.0))
+            .collect();
+        let start = Instant::now();
+        for _ in 0..TICKS {
+            for (from, to) in &segments {
+                black_box(level.tiles_on_segment(*from, *to));
+            }
+        }
+        let segment_query = start.elapsed();
+
+        let mut bullets: Vec<Bullet> = rects.iter().map(|r| Bullet::new(vec2(r.x, r.y))).collect();
+        let start = Instant::now();
+        for _ in 0..TICKS {
+            for b in &mut bullets {
+                black_box(b.update(1.0 / 120.0, level, &rules));
+            }
+        }
+        let bullet_update = start.elapsed();
+
+        println!(
+            "{:>6} {:>11.1}MS {:>11.1}MS {:>11.1}MS {:>11.1}MS",
+            count,
+            full_scan.as_secs_f64() * 1000.0,
+            rect_query.as_secs_f64() * 1000.0,
+            segment_query.as_secs_f64() * 1000.0,
+            bullet_update.as_secs_f64() * 1000.0,
+        );
+    }
+    println!("(TOTAL FOR {} TICKS)", TICKS);
+}
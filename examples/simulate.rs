@@ -0,0 +1,70 @@
+// Plays every level of a pack with a very simple bot, without a window, and prints how it went.
+// Useful for balance testing on machines with no gpu:
+//   cargo run --release --example simulate --no-default-features -- [games per level]
+
+use breakout::game::{level_pack::LevelPack, world::{ball::BALL_SIZE, input::InputFrame, rules::GameRules, Lives, World, WorldEvent, TICK_RATE}};
+
+// Give up on a game after 10 minutes of game time
+const MAX_TICKS: usize = 10 * 60 * TICK_RATE as usize;
+
+fn main() {
+    let games: u64 = std::env::args().nth(1).and_then(|a| a.parse().ok()).unwrap_or(100);
+    let level_pack = LevelPack::load_from_file(include_bytes!("../ALBUMS I LIKE.brk").to_vec()).expect("the level pack should be valid");
+
+    println!("{} BY {}, {} GAMES PER LEVEL", level_pack.name(), level_pack.author(), games);
+    for (i, level) in level_pack.levels().iter().enumerate() {
+        let (mut cleared, mut total_score, mut total_ticks) = (0, 0, 0);
+
+        for seed in 0..games {
+            let mut world = World::new(level.clone(), i, None, None, Lives::Default, None, seed, GameRules::default());
+            let mut ticks = 0;
+            while ticks < MAX_TICKS && !world.level_complete() {
+                let events = world.update(bot_input(&world, ticks));
+                ticks += 1;
+
+                if events.contains(&WorldEvent::GameOver) {
+                    break;
+                }
+                if events.contains(&WorldEvent::BallStuck) {
+                    world.give_free_ball();
+                }
+            }
+
+            if world.level_complete() {
+                cleared += 1;
+            }
+            total_score += world.score();
+            total_ticks += ticks;
+        }
+
+        println!(
+            "{:>2} {:<16} CLEARED {:>4}/{:<4} AVG SCORE {:>6} AVG TIME {:>6.1}S",
+            i + 1,
+            level.name(),
+            cleared,
+            games,
+            total_score / games as usize,
+            total_ticks as f32 / games as f32 / TICK_RATE,
+        );
+    }
+}
+
+// Follows the lowest falling ball, shoots whenever it can and launches carried balls straight away
+fn bot_input(world: &World, tick: usize) -> InputFrame {
+    let paddle = world.paddle();
+    let paddle_center = paddle.x() + paddle.width() / 2.0;
+
+    let target = world.balls()
+        .iter()
+        .filter(|b| b.vel().y > 0.0)
+        .max_by(|a, b| a.pos().y.total_cmp(&b.pos().y))
+        .map(|b| b.pos().x + BALL_SIZE / 2.0)
+        .unwrap_or(paddle_center);
+
+    InputFrame {
+        move_axis: ((target - paddle_center) / 4.0).clamp(-1.0, 1.0),
+        fire: true,
+        carry_released: tick.is_multiple_of(TICK_RATE as usize),
+        ..Default::default()
+    }
+}
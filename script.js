@@ -103,10 +103,19 @@ async function load_level_file(url) {
     }
 }
 
+// v2 packs and later start with a 5 byte header (0x00 'BRK' version) before the name, v1 packs start with the name
+function pack_name_offset(view) {
+    if (view[0] == 0 && view[1] == 66 && view[2] == 82 && view[3] == 75) {
+        return 5;
+    }
+    return 0;
+}
+
 function add_level_button(arrayBuffer, closable, load) {
     let view = new Uint8Array(arrayBuffer);
+    let offset = pack_name_offset(view);
     let name_bytes = [];
-    for (var i = 0; i < 16; i++) {
+    for (var i = offset; i < offset + 16; i++) {
         if (view[i] != 255) {
             name_bytes.push(view[i]);
         } else {
@@ -115,7 +124,7 @@ function add_level_button(arrayBuffer, closable, load) {
     }
     let name = String.fromCharCode(...name_bytes);
     let author_bytes = [];
-    for (var i = 16; i < 32; i++) {
+    for (var i = offset + 16; i < offset + 32; i++) {
         if (view[i] != 255) {
             author_bytes.push(view[i]);
         } else {
@@ -170,6 +179,9 @@ register_plugin = function (importObject) {
     // make the function available to call from rust
     importObject.env.js_send_level_bytes = js_send_level_bytes;
     importObject.env.js_recv_level_bytes = js_recv_level_bytes;
+    importObject.env.js_save_session = js_save_session;
+    importObject.env.js_load_session = js_load_session;
+    importObject.env.js_clear_session = js_clear_session;
 }
 
 // register this plugin in miniquad, required to make plugin's functions available from rust
@@ -186,8 +198,9 @@ function js_recv_level_bytes(bytes) {
     const byteArray = consume_js_object(bytes);
 
     let view = new Uint8Array(byteArray);
+    let offset = pack_name_offset(view);
     let pack_name_bytes = [];
-    for (var i = 0; i < 16; i++) {
+    for (var i = offset; i < offset + 16; i++) {
         if (view[i] != 255) {
             pack_name_bytes.push(view[i]);
         } else {
@@ -218,4 +231,37 @@ function js_recv_level_bytes(bytes) {
     // Clean up by revoking the object URL and removing the link
     URL.revokeObjectURL(link.href);
     document.body.removeChild(link);
-}
\ No newline at end of file
+}
+
+// The saved session (a game left halfway through) lives in local storage, as base64 since it can only hold strings
+const SESSION_KEY = "breakout_session";
+
+function js_save_session(bytes) {
+    let view = new Uint8Array(consume_js_object(bytes));
+    let string = "";
+    for (let i = 0; i < view.length; i++) {
+        string += String.fromCharCode(view[i]);
+    }
+    try {
+        localStorage.setItem(SESSION_KEY, btoa(string));
+    } catch (error) {
+        console.warn("Couldn't save session:", error);
+    }
+}
+
+function js_load_session() {
+    let saved = localStorage.getItem(SESSION_KEY);
+    if (saved == null) {
+        return -1;
+    }
+    let string = atob(saved);
+    let view = new Uint8Array(string.length);
+    for (let i = 0; i < string.length; i++) {
+        view[i] = string.charCodeAt(i);
+    }
+    return js_object(view);
+}
+
+function js_clear_session() {
+    localStorage.removeItem(SESSION_KEY);
+}
@@ -1,8 +1,8 @@
 use std::collections::HashMap;
 
-use macroquad::{color::WHITE, input::{clear_input_queue, is_key_pressed, is_mouse_button_pressed, KeyCode, MouseButton}, math::{vec2, Rect, Vec2}, shapes::{draw_line, draw_rectangle, draw_rectangle_lines}, texture::{draw_texture_ex, DrawTextureParams, Texture2D}};
+use macroquad::{color::{Color, WHITE}, input::{clear_input_queue, is_key_pressed, is_mouse_button_pressed, KeyCode, MouseButton}, math::{vec2, Rect, Vec2}, shapes::{draw_line, draw_rectangle, draw_rectangle_lines}, texture::{draw_texture_ex, DrawTextureParams, Texture2D}};
 
-use crate::{game::world::{level::{Level, Tile, TILE_HEIGHT, TILE_WIDTH}, BG_COL}, gui::{Button, ButtonDetail, Gui, Id, TextField, BUTTON_COL_HOVER, BUTTON_COL_IDLE, BUTTON_DETAIL_GREY, BUTTON_DETAIL_HELP, DARKEN_BACKGROUND, GRID_COL}, text_renderer::{render_text, TextAlign}};
+use crate::{game::world::{level::{Level, LevelMeta, MotionAxis, RowMotion, Tile, LEVEL_HINT_LEN, MAX_HIT_POINTS, TILE_HEIGHT, TILE_WIDTH}, powerup::{PlacedPowerup, PowerupKind}, BG_COL}, gui::{Button, ButtonDetail, Gui, Id, TextField, BUTTON_COL_HOVER, BUTTON_COL_IDLE, BUTTON_DETAIL_GREY, BUTTON_DETAIL_HELP, DARKEN_BACKGROUND, GRID_COL}, text_renderer::{render_text, TextAlign}};
 
 use super::editor_level_pack::EditorLevelPack;
 
@@ -22,8 +22,41 @@ const TILES_BUTTONS: &[Tile] = &[
     Tile::StoneCracked,
     Tile::Metal,
     Tile::Gold,
+    Tile::Explosive,
+    Tile::PortalA,
+    Tile::PortalB,
     Tile::Air
 ];
+// There's only room for 16 buttons along the bottom, so the tiles are split into pages, with the last button flipping between them.
+// After the tiles come the pages of powerups, for placing under tiles
+const PALETTE_SLOTS: usize = 15;
+const POWERUP_BUTTONS: &[PlacedPowerup] = &[
+    PlacedPowerup::Random,
+    PlacedPowerup::Kind(PowerupKind::PaddleCarry),
+    PlacedPowerup::Kind(PowerupKind::PaddleGun),
+    PlacedPowerup::Kind(PowerupKind::PaddleGrow),
+    PlacedPowerup::Kind(PowerupKind::Zap),
+    PlacedPowerup::Kind(PowerupKind::BallsFive),
+    PlacedPowerup::Kind(PowerupKind::BallsTrail),
+    PlacedPowerup::Kind(PowerupKind::BallsSafe),
+    PlacedPowerup::Kind(PowerupKind::PaddleMagnet),
+    PlacedPowerup::Kind(PowerupKind::BallsFire),
+    PlacedPowerup::Kind(PowerupKind::SlowTime),
+    PlacedPowerup::Kind(PowerupKind::ExtraLife),
+    PlacedPowerup::Kind(PowerupKind::PaddleShrink),
+    PlacedPowerup::Kind(PowerupKind::BallsFast),
+    PlacedPowerup::Kind(PowerupKind::ControlsReversed),
+];
+const POWERUP_BUTTONS_ID: Id = 30;
+
+// The backgrounds a level can pick from, after the normal one
+const BACKGROUNDS: &[[u8; 3]] = &[
+    [58, 25, 31],
+    [25, 58, 37],
+    [46, 25, 58],
+    [58, 46, 25],
+    [10, 10, 14],
+];
 
 const ARROW_TEXTURE: Rect = Rect { x: 157.0, y: 8.0, w: 7.0, h: 9.0 };
 const UNDO_TEXTURE: Rect = Rect { x: 165.0, y: 8.0, w: 7.0, h: 6.0 };
@@ -42,6 +75,10 @@ pub enum Confirmation {
     Exit,
     Save,
     Help,
+    // The level's par time, ball speed etc. Changes happen straight away, so it just has a 'DONE' button
+    Info,
+    // How a row moves, the same as 'Info'
+    RowMotion(usize),
 }
 
 pub struct EditorGui {
@@ -49,26 +86,25 @@ pub struct EditorGui {
 
     confirmation: Confirmation,
     confirmation_popup: Confirmation,
+    palette_page: usize,
 
     text_fields: HashMap<Id, TextField>,
     active_text_field: Option<Id>,
     text_field_flash: f32,
 }
 
+impl Default for EditorGui {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl EditorGui {
     pub fn new() -> Self {
         let view_size = Level::view_size();
         let mut buttons = HashMap::new();
 
-        for (i, tile) in TILES_BUTTONS.iter().enumerate() {
-            let rect = Rect {
-                x: i as f32 * (TILE_WIDTH + 1.0),
-                y: view_size.y - (TILE_HEIGHT + 1.0),
-                w: TILE_WIDTH + 1.0,
-                h: TILE_HEIGHT + 1.0,
-            };
-            buttons.insert(i as Id, Button::new(rect, ButtonDetail::Icon(tile.texture_rect()), vec2(0.0, 0.0)));
-        }
+        buttons.insert(108, Button::new(palette_slot_rect(PALETTE_SLOTS), ButtonDetail::Icon(LEVEL_NEXT_TEXTURE), vec2(2.0, 1.0))); // Palette page
         buttons.insert(100, Button::new(Rect::new(view_size.x - 97.0, 0.0, 97.0, 7.0), ButtonDetail::None, vec2(0.0, 0.0))); // Name
         let mut text_fields = HashMap::new();
         text_fields.insert(100, TextField::new(vec2(view_size.x - 97.0, 0.0)));
@@ -79,6 +115,8 @@ impl EditorGui {
         buttons.insert(104, Button::new(Rect::new(view_size.x - 20.0, view_size.y - 38.0,  9.0, 8.0), ButtonDetail::Icon(UNDO_TEXTURE), vec2(1.0, 1.0)));
         buttons.insert(105, Button::new(Rect::new(view_size.x - 10.0, view_size.y - 38.0,  9.0, 8.0), ButtonDetail::Icon(REDO_TEXTURE), vec2(1.0, 1.0)));
         buttons.insert(106, Button::new(Rect::new(view_size.x - 60.0, view_size.y - 38.0, 39.0, 8.0), ButtonDetail::Text(String::from("HELP??")), vec2(2.0, 1.0)));
+        buttons.insert(107, Button::new(Rect::new(view_size.x - 92.0, view_size.y - 38.0, 27.0, 8.0), ButtonDetail::None, vec2(2.0, 1.0))); // Hit points, the text changes so it's drawn separately
+        buttons.insert(109, Button::new(Rect::new(37.0, 0.0, 25.0, 7.0), ButtonDetail::None, vec2(1.0, 0.0))); // Level info, there's no room for an outline so it's drawn like the name
 
         buttons.insert(200, Button::new(Rect::new(87.0, view_size.y - 37.0, 9.0, 7.0), ButtonDetail::Icon(LEVEL_ADD_TEXTURE), vec2(2.0, 1.0)));
         buttons.insert(201, Button::new(Rect::new(77.0, view_size.y - 37.0, 9.0, 7.0), ButtonDetail::Icon(LEVEL_NEXT_TEXTURE), vec2(1.0, 1.0)));
@@ -87,14 +125,37 @@ impl EditorGui {
         buttons.insert(204, Button::new(Rect::new(34.0, view_size.y - 37.0, 9.0, 7.0), ButtonDetail::Icon(SHIFT_PREV_TEXTURE), vec2(1.0, 1.0)));
         buttons.insert(205, Button::new(Rect::new(1.0, view_size.y - 29.0, 39.0, 8.0), ButtonDetail::Text(String::from("DELETE")), vec2(2.0, 1.0)));
 
-        Self {
+        let mut editor_gui = Self {
             gui: Gui::new(buttons),
             confirmation: Confirmation::None,
             confirmation_popup: Confirmation::None,
+            palette_page: 0,
             
             text_fields,
             active_text_field: None,
             text_field_flash: 0.0,
+        };
+        editor_gui.insert_palette_buttons();
+        editor_gui
+    }
+
+    // Only the current page of the palette has its buttons in the gui.
+    // Every page uses the same slots, so a button from another page would be under the mouse too, and could take the click
+    fn insert_palette_buttons(&mut self) {
+        let buttons = self.gui.buttons_mut();
+        for (i, tile) in TILES_BUTTONS.iter().enumerate() {
+            match i / PALETTE_SLOTS == self.palette_page {
+                true => buttons.insert(i as Id, Button::new(palette_slot_rect(i % PALETTE_SLOTS), ButtonDetail::Icon(tile.texture_rect()), vec2(0.0, 0.0))),
+                false => buttons.remove(&(i as Id)),
+            };
+        }
+        let powerup_page = self.palette_page.checked_sub(Self::tile_pages());
+        let buttons = self.gui.buttons_mut();
+        for i in 0..POWERUP_BUTTONS.len() {
+            match powerup_page == Some(i / PALETTE_SLOTS) {
+                true => buttons.insert(POWERUP_BUTTONS_ID + i as Id, Button::new(palette_slot_rect(i % PALETTE_SLOTS), ButtonDetail::None, vec2(0.0, 0.0))),
+                false => buttons.remove(&(POWERUP_BUTTONS_ID + i as Id)),
+            };
         }
     }
 
@@ -145,36 +206,60 @@ impl EditorGui {
         self.confirmation == Confirmation::LevelDelete
     }
 
+    fn tile_pages() -> usize {
+        TILES_BUTTONS.len().div_ceil(PALETTE_SLOTS)
+    }
+    fn palette_pages() -> usize {
+        Self::tile_pages() + POWERUP_BUTTONS.len().div_ceil(PALETTE_SLOTS)
+    }
+    // Whether the palette's on a powerups page, so clicking the grid places powerups instead of tiles
+    pub fn placing_powerups(&self) -> bool {
+        self.palette_page >= Self::tile_pages()
+    }
+
+    pub fn editing_text(&self) -> bool {
+        self.active_text_field.is_some()
+    }
+
     pub fn stop_editing_name(&mut self) {
         if self.active_text_field.is_some_and(|id| id == 100) {
             self.active_text_field = None;
         }
     }
 
-    pub fn update(&mut self, mouse_pos: Vec2, level_pack: &mut EditorLevelPack, draw_type: &mut Tile) {
+    pub fn update(&mut self, mouse_pos: Vec2, level_pack: &mut EditorLevelPack, draw_type: &mut Tile, draw_hit_points: &mut u8, draw_powerup: &mut PlacedPowerup) {
         self.confirmation = Confirmation::None;
 
         let update_only: Option<&[Id]> = match self.confirmation_popup {
             Confirmation::None => None,
             Confirmation::Help => Some(&[106]),
+            Confirmation::Info => Some(&[400, 401, 402, 403, 404, 405, 406, 407, 408, 410]),
+            Confirmation::RowMotion(_) => Some(&[420, 421, 422, 423, 424, 425, 426, 427, 428]),
             _ => Some(&[300, 301, 302, 303]),
         };
         self.gui.update(mouse_pos, update_only);
 
         // Confirmation popup
         let prev_confirmation_popup = self.confirmation_popup;
-        self.confirmation_popup = match (self.button_released(101), self.button_released(102), self.button_released(205), self.button_released(106), self.confirmation_popup == Confirmation::Help) {
-            (true, _, _, _, _) => Confirmation::Exit,
-            (_, true, _, _, _) => Confirmation::Save,
-            (_, _, true, _, _) => Confirmation::LevelDelete,
-            (_, _, _, true, false) => Confirmation::Help,
-            (_, _, _, true, true)  => Confirmation::None,
+        self.confirmation_popup = match (self.button_released(101), self.button_released(102), self.button_released(205), self.button_released(106), self.confirmation_popup == Confirmation::Help, self.button_released(109)) {
+            (true, _, _, _, _, _) => Confirmation::Exit,
+            (_, true, _, _, _, _) => Confirmation::Save,
+            (_, _, true, _, _, _) => Confirmation::LevelDelete,
+            (_, _, _, true, false, _) => Confirmation::Help,
+            (_, _, _, true, true, _)  => Confirmation::None,
+            (_, _, _, _, _, true) => Confirmation::Info,
             _ => self.confirmation_popup,
         };
 
         // A new popup!
         if self.confirmation_popup != Confirmation::None && prev_confirmation_popup == Confirmation::None {
-            if self.confirmation_popup != Confirmation::Help {
+            if self.confirmation_popup == Confirmation::Info {
+                self.insert_step_buttons(400, 4);
+                self.gui.buttons_mut().insert(408, Button::new(Rect::new(83.0, 127.0, 27.0, 8.0), ButtonDetail::Text(String::from("DONE")), vec2(2.0, 1.0)));
+                self.gui.buttons_mut().insert(410, Button::new(Rect::new(12.0, 116.0, LEVEL_HINT_LEN as f32 * 6.0 + 1.0, 7.0), ButtonDetail::None, vec2(0.0, 0.0))); // Hint
+                self.text_fields.insert(410, TextField::with_len(vec2(12.0, 116.0), LEVEL_HINT_LEN));
+            }
+            else if self.confirmation_popup != Confirmation::Help {
                 self.gui.buttons_mut().insert(300, Button::new(Rect::new(70.0, 95.0, 21.0, 8.0), ButtonDetail::Text(String::from("YES")), vec2(2.0, 1.0)));
                 self.gui.buttons_mut().insert(301, Button::new(Rect::new(100.0, 95.0, 21.0, 8.0), ButtonDetail::Text(String::from("NO")), vec2(5.0, 1.0)));
             }
@@ -189,8 +274,38 @@ impl EditorGui {
             }
         }
         // Updating the popup...
-        if self.confirmation_popup != Confirmation::None && self.confirmation_popup != Confirmation::Help {
-            let (yes, no) = (self.button_released(300), self.button_released(301));
+        if let Confirmation::RowMotion(row) = self.confirmation_popup {
+            let mut motion = level_pack.level().motions()[row];
+            update_row_motion(&mut motion, |id| self.step(id));
+            if motion != level_pack.level().motions()[row] {
+                level_pack.timewarp_save_previous_state();
+                level_pack.timewarp_push_current_state();
+                level_pack.level_mut().motions_mut()[row] = motion;
+            }
+            if self.button_released(428) {
+                for id in 420..=428 {
+                    self.gui.buttons_mut().remove(&id);
+                }
+                self.confirmation_popup = Confirmation::None;
+            }
+        }
+        else if self.confirmation_popup == Confirmation::Info {
+            update_level_info(level_pack.level_mut().meta_mut(), |id| self.step(id));
+            if self.button_released(408) {
+                for id in 400..=410 {
+                    self.gui.buttons_mut().remove(&id);
+                }
+                self.text_fields.remove(&410);
+                if self.active_text_field == Some(410) {
+                    self.active_text_field = None;
+                }
+                self.confirmation_popup = Confirmation::None;
+            }
+        }
+        else if self.confirmation_popup != Confirmation::None && self.confirmation_popup != Confirmation::Help {
+            // Saving with unpaired portals would make a pack that doesn't work, so 'YES' does nothing
+            let blocked = self.confirmation_popup == Confirmation::Save && level_pack.unpaired_portals().is_some();
+            let (yes, no) = (self.button_released(300) && !blocked, self.button_released(301));
             if yes {
                 self.confirmation = self.confirmation_popup;
             }
@@ -208,7 +323,7 @@ impl EditorGui {
         
         // Text fields
         let mut hovering_any = false; 
-        for id in [100, 302, 303] {
+        for id in [100, 302, 303, 410] {
             if self.gui.button(id).is_some_and(|b| b.rect().contains(mouse_pos)) {
                 hovering_any = true;
             }
@@ -226,7 +341,12 @@ impl EditorGui {
         }
         if let Some(id) = self.active_text_field {
             let exit = if let Some(text_field) = self.text_fields.get_mut(&id) {
-                let update_text = if id == 100 { level_pack.level_mut().name_mut() } else if id == 302 { level_pack.name_mut() } else { level_pack.author_mut() };
+                let update_text = match id {
+                    100 => level_pack.level_mut().name_mut(),
+                    302 => level_pack.name_mut(),
+                    303 => level_pack.author_mut(),
+                    _   => &mut level_pack.level_mut().meta_mut().hint,
+                };
                 text_field.update(update_text)
             } else { false };
             if exit {
@@ -241,14 +361,60 @@ impl EditorGui {
         self.text_field_flash = (self.text_field_flash + macroquad::time::get_frame_time()) % 0.4;
 
         // Selecting tile
+        if self.button_released(108) {
+            self.palette_page = (self.palette_page + 1) % Self::palette_pages();
+            self.insert_palette_buttons();
+        }
         for (id, tile) in TILES_BUTTONS.iter().enumerate() {
             if self.button_released(id as Id) {
                 *draw_type = *tile;
             }
         }
+        for (i, powerup) in POWERUP_BUTTONS.iter().enumerate() {
+            if self.button_released(POWERUP_BUTTONS_ID + i as Id) {
+                *draw_powerup = *powerup;
+            }
+        }
+
+        // Selecting hit points, clicking goes up and wraps around, or the number keys pick one. 0 leaves it up to the tile's material
+        if self.button_released(107) {
+            *draw_hit_points = (*draw_hit_points + 1) % (MAX_HIT_POINTS + 1);
+        }
+        if self.active_text_field.is_none() && !self.popup_open() {
+            for (hit_points, key) in [KeyCode::Key0, KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4, KeyCode::Key5, KeyCode::Key6, KeyCode::Key7, KeyCode::Key8, KeyCode::Key9].into_iter().enumerate() {
+                if is_key_pressed(key) {
+                    *draw_hit_points = hit_points as u8;
+                }
+            }
+        }
     }
 
-    pub fn draw(&self, texture: &Texture2D, level_pack: &EditorLevelPack, draw_type: &Tile) {
+    // Rows of -/+ buttons for popups that step through values, 'first' and 'first + 1' for the first row and so on
+    fn insert_step_buttons(&mut self, first: Id, rows: usize) {
+        for i in 0..rows {
+            let y = 63.0 + i as f32 * 11.0;
+            self.gui.buttons_mut().insert(first + i as Id * 2,     Button::new(Rect::new(104.0, y, 9.0, 8.0), ButtonDetail::Text(String::from("-")), vec2(2.0, 1.0)));
+            self.gui.buttons_mut().insert(first + i as Id * 2 + 1, Button::new(Rect::new(164.0, y, 9.0, 8.0), ButtonDetail::Text(String::from("+")), vec2(2.0, 1.0)));
+        }
+    }
+    // -1 if 'minus' was clicked, 1 if the '+' after it was
+    fn step(&self, minus: Id) -> i32 {
+        match (self.button_released(minus), self.button_released(minus + 1)) {
+            (true, false) => -1,
+            (false, true) => 1,
+            _ => 0,
+        }
+    }
+
+    // Opens the popup for how 'row' moves
+    pub fn open_row_motion(&mut self, row: usize) {
+        self.confirmation_popup = Confirmation::RowMotion(row);
+        self.active_text_field = None;
+        self.insert_step_buttons(420, 4);
+        self.gui.buttons_mut().insert(428, Button::new(Rect::new(83.0, 108.0, 27.0, 8.0), ButtonDetail::Text(String::from("DONE")), vec2(2.0, 1.0)));
+    }
+
+    pub fn draw(&self, texture: &Texture2D, level_pack: &EditorLevelPack, draw_type: &Tile, draw_hit_points: u8, draw_powerup: &PlacedPowerup) {
         let level = level_pack.level();
         let timewarp = level_pack.timewarp();
 
@@ -285,6 +451,39 @@ impl EditorGui {
             }
         }
 
+        // Powerup buttons
+        for (i, powerup) in POWERUP_BUTTONS.iter().enumerate() {
+            let button = match self.gui.button(POWERUP_BUTTONS_ID + i as Id) {
+                Some(b) => b,
+                None => continue,
+            };
+            let rect = button.rect();
+            let y_offset = match button.idle() {
+                true => 0.0,
+                false => -1.0,
+            };
+            // Capsules are wider than a tile, so their ends are cut off to fit in the slot
+            match powerup {
+                PlacedPowerup::Kind(kind) => {
+                    let source = kind.texture_rect();
+                    draw_texture_ex(texture, rect.x, rect.y + y_offset, WHITE, DrawTextureParams {
+                        source: Some(Rect::new(source.x + 1.0, source.y, source.w - 2.0, source.h)),
+                        ..Default::default()
+                    });
+                }
+                _ => {
+                    draw_rectangle(rect.x, rect.y + y_offset, TILE_WIDTH, TILE_HEIGHT + 1.0, Color::new(0.0, 0.0, 0.0, 0.6));
+                    render_text("?", vec2(rect.x + 3.0, rect.y + y_offset), WHITE, TextAlign::Left, texture);
+                }
+            }
+            if *draw_powerup == *powerup {
+                draw_texture_ex(texture, rect.x + (rect.w - 1.0 - 7.0) / 2.0, rect.y - 9.0 - 3.0, WHITE, DrawTextureParams {
+                    source: Some(ARROW_TEXTURE),
+                    ..Default::default()
+                });
+            }
+        }
+
         let field_flash = self.text_field_flash <= 0.2;
 
         if let Some(name_field) = self.text_fields.get(&100) {
@@ -293,7 +492,7 @@ impl EditorGui {
 
         // Other buttons
         for id in [
-            101, 102, 103, 104, 105, 106, 200, 201, 202, 203, 204, 205,
+            101, 102, 103, 104, 105, 106, 107, 108, 200, 201, 202, 203, 204, 205,
         ] {
             let button = match self.gui.button(id) {
                 Some(b) => b,
@@ -312,6 +511,10 @@ impl EditorGui {
             let detail_col = if grey { BUTTON_DETAIL_GREY } else if id == 106 { BUTTON_DETAIL_HELP } else { WHITE };
 
             button.draw(texture, detail_col, bg_col, GRID_COL);
+            if id == 107 {
+                let hit_points = if draw_hit_points == 0 { String::from("-") } else { draw_hit_points.to_string() };
+                render_text(&format!("HP {}", hit_points), button.rect().point() + button.detail_pos(), detail_col, TextAlign::Left, texture);
+            }
         }
 
 
@@ -319,6 +522,14 @@ impl EditorGui {
         render_text(&format!("LVL {:0>2}/{:0>2}", level_pack.current() + 1, level_pack.level_count()), vec2(43.0, view_size.y - 28.0), WHITE, TextAlign::Left, texture);
 
         render_text(&String::from("EDITOR"), vec2(0.0, 0.0), WHITE, TextAlign::Left, texture);
+        if let Some(button) = self.gui.button(109) {
+            let rect = button.rect();
+            if !button.idle() {
+                draw_rectangle(rect.x, rect.y, rect.w, rect.h, BUTTON_COL_HOVER);
+            }
+            render_text(&String::from("INFO"), rect.point() + button.detail_pos(), BUTTON_DETAIL_HELP, TextAlign::Left, texture);
+        }
+        render_text(&format!("{}X{}", level.width(), level.height()), vec2(view_size.x, 7.0), BUTTON_DETAIL_GREY, TextAlign::Right, texture);
         render_text(&String::from("PRESS ESC TO PLAY LEVEL."), vec2(0.0, 7.0), WHITE, TextAlign::Left, texture);
 
 
@@ -328,6 +539,8 @@ impl EditorGui {
             let rect = match self.confirmation_popup {
                 Confirmation::Help => None,
                 Confirmation::Save => Some(Rect::new(26.0, 63.0, 143.0, 43.0)),
+                Confirmation::Info => Some(Rect::new(6.0, 50.0, 180.0, 88.0)),
+                Confirmation::RowMotion(_) => Some(Rect::new(6.0, 50.0, 180.0, 72.0)),
                 _ => Some(Rect::new(49.0, 68.0, 93.0, 38.0)),
             };
             if let Some(r) = rect {
@@ -345,13 +558,67 @@ impl EditorGui {
                 render_text(&String::from("  THIS CANNOT  "), vec2(51.0, 78.0), WHITE, TextAlign::Left, texture);
                 render_text(&String::from("   BE UNDONE   "), vec2(51.0, 86.0), WHITE, TextAlign::Left, texture);
             }
+            let unpaired_portals = level_pack.unpaired_portals();
             if self.confirmation_popup == Confirmation::Save {
-                render_text(&String::from("SAVE LEVEL PACK"), vec2(51.0, 65.0), WHITE, TextAlign::Left, texture);
+                match unpaired_portals {
+                    None => render_text(&String::from("SAVE LEVEL PACK"), vec2(51.0, 65.0), WHITE, TextAlign::Left, texture),
+                    Some(l) => render_text(&format!("LVL {:0>2} PORTALS UNPAIRED", l + 1), vec2(97.5, 65.0), BUTTON_DETAIL_HELP, TextAlign::Center, texture),
+                }
             }
 
 
+            if self.confirmation_popup == Confirmation::Info {
+                let meta = level.meta();
+                render_text(&String::from("LEVEL INFO"), vec2(96.0, 52.0), WHITE, TextAlign::Center, texture);
+                for (i, (name, value)) in [
+                    ("PAR TIME",   meta.par_time.map(|t| format!("{:0>2}:{:0>2}", t as usize / 60, t as usize % 60))),
+                    ("PAR SCORE",  meta.par_score.map(|s| s.to_string())),
+                    ("BALL SPEED", meta.ball_speed.map(|s| format!("{:.1}X", s))),
+                    ("BACKGROUND", None),
+                ].into_iter().enumerate() {
+                    match (i, meta.background_color()) {
+                        (3, Some(col)) => {
+                            let y = draw_step_row(texture, i, name, Some(String::new()));
+                            draw_rectangle_lines(124.0, y - 1.0, 30.0, 8.0, 2.0, GRID_COL);
+                            draw_rectangle(125.0, y, 28.0, 6.0, col);
+                        }
+                        _ => { draw_step_row(texture, i, name, value); }
+                    }
+                }
+                render_text(&String::from("HINT BEFORE THE FIRST BALL:"), vec2(12.0, 108.0), WHITE, TextAlign::Left, texture);
+                for id in 400..=408 {
+                    if let Some(b) = self.gui.button(id) {
+                        b.draw(texture, WHITE, if b.hovered() { BUTTON_COL_HOVER } else { BUTTON_COL_IDLE }, GRID_COL);
+                    }
+                }
+                if let Some(field) = self.text_fields.get(&410) {
+                    field.draw(texture, &meta.hint, "", field_flash && self.active_text_field == Some(410), BUTTON_COL_HOVER, BUTTON_DETAIL_GREY);
+                }
+            }
+            if let Confirmation::RowMotion(row) = self.confirmation_popup {
+                let motion = level.motions()[row];
+                render_text(&format!("ROW {:0>2} MOTION", row + 1), vec2(96.0, 52.0), WHITE, TextAlign::Center, texture);
+                let moving = |v: String| Some(v).filter(|_| motion.moving());
+                draw_step_row(texture, 0, "MOVES", match motion.axis {
+                    MotionAxis::None => None,
+                    MotionAxis::Horizontal => Some(String::from("SIDEWAYS")),
+                    MotionAxis::Vertical => Some(String::from("UP/DOWN")),
+                });
+                draw_step_row(texture, 1, "DISTANCE", moving(format!("{}PX", motion.distance)));
+                draw_step_row(texture, 2, "PERIOD", moving(format!("{:.1}S", motion.period)));
+                draw_step_row(texture, 3, "PHASE", moving(format!("{:.2}", motion.phase)));
+                for id in 420..=428 {
+                    if let Some(b) = self.gui.button(id) {
+                        b.draw(texture, WHITE, if b.hovered() { BUTTON_COL_HOVER } else { BUTTON_COL_IDLE }, GRID_COL);
+                    }
+                }
+            }
+
             for id in [300, 301] {
-                self.gui.button(id).map(|b| b.draw(texture, WHITE, if b.hovered() { BUTTON_COL_HOVER } else { BUTTON_COL_IDLE }, GRID_COL));
+                if let Some(b) = self.gui.button(id) {
+                    let grey = id == 300 && self.confirmation_popup == Confirmation::Save && unpaired_portals.is_some();
+                    b.draw(texture, if grey { BUTTON_DETAIL_GREY } else { WHITE }, if b.hovered() { BUTTON_COL_HOVER } else { BUTTON_COL_IDLE }, GRID_COL);
+                }
             }
             for (id, text, name) in [
                 (302, level_pack.name(), String::from("NAME:")),
@@ -375,20 +642,119 @@ impl EditorGui {
 
     fn render_help(&self, texture: &Texture2D) {
         let help_points: &[(Vec2, Vec2, &[&str], TextAlign)] = &[
-            (vec2(190.0, 3.0), vec2(185.0, 30.0), &["CLICK THIS TO CHANGE THE", "LEVEL NAME (TYPE). CLICK AGAIN ", "OR PRESS 'ENTER' TO DESELECT"], TextAlign::Right),
+            (vec2(190.0, 3.0), vec2(185.0, 30.0), &["CLICK THIS TO CHANGE THE", "LEVEL NAME (TYPE). CLICK AGAIN ", "OR PRESS 'ENTER' TO DESELECT", "ARROW KEYS RESIZE THE LEVEL", "'INFO' HAS PARS, SPEED + HINT", "'M' OVER A ROW MAKES IT MOVE"], TextAlign::Right),
             (vec2(92.0, 171.0), vec2(92.0, 160.0), &["ADDS A NEW LEVEL", "TO THE PACK."], TextAlign::Left),
             (vec2(77.0, 171.0), vec2(92.0, 140.0), &["EDIT NEXT / PREV", "LEVEL IN PACK."], TextAlign::Left),
             (vec2(44.0, 171.0), vec2(68.0, 115.0), &["SHIFTS THE CURRENT", "LEVEL BACK / FORWARD", "IN THE PACK'S ORDER."], TextAlign::Left),
-            (vec2(31.0, 179.0), vec2(80.0, 90.0), &["DELETE THE CURRENT", "LEVEL (WITH POPUP)"], TextAlign::Left),
-            (vec2(5.0, 196.0), vec2(24.0, 63.0), &["TILE SELECTOR - LEFT CLICK", "TO SELECT TILE. DRAW/ERASE", "ON GRID WITH LEFT/RIGHT"], TextAlign::Left),
+            (vec2(31.0, 179.0), vec2(80.0, 97.0), &["DELETE THE CURRENT", "LEVEL (WITH POPUP)"], TextAlign::Left),
+            (vec2(5.0, 196.0), vec2(24.0, 69.0), &["TILE SELECTOR - LEFT CLICK", "TO SELECT TILE. DRAW/ERASE", "ON GRID WITH LEFT/RIGHT", "HIT POINTS: HP BUTTON / 0-9", "ARROW FOR TILES / POWERUPS", "PORTAL A/B PAIR IN ORDER"], TextAlign::Left),
         ];
         for (a, b, lines, align) in help_points {
             let (a, b) = (*a + vec2(-0.5, 1.0), *b + vec2(1.0, -0.5));
             draw_line(a.x, a.y, a.x, b.y+0.5, 1.0, WHITE);
             draw_line(a.x-0.5, b.y, b.x, b.y, 1.0, WHITE);
             for (i, line) in lines.iter().enumerate() {
-                render_text(&line.to_string(), b + vec2(1.0, i as f32 * 7.0 - lines.len() as f32 * 7.0 * 0.5), WHITE, *align, texture);
+                render_text(line, b + vec2(1.0, i as f32 * 7.0 - lines.len() as f32 * 7.0 * 0.5), WHITE, *align, texture);
+            }
+        }
+    }
+}
+
+// A name and its value for the 'nth' row of -/+ buttons, None is shown as 'NONE'. Returns the y of the row
+fn draw_step_row(texture: &Texture2D, nth: usize, name: &str, value: Option<String>) -> f32 {
+    let y = 64.0 + nth as f32 * 11.0;
+    render_text(name, vec2(12.0, y), WHITE, TextAlign::Left, texture);
+    let (value, col) = match value {
+        Some(v) => (v, WHITE),
+        None => (String::from("NONE"), BUTTON_DETAIL_GREY),
+    };
+    render_text(&value, vec2(139.0, y), col, TextAlign::Center, texture);
+    y
+}
+
+// The -/+ buttons in the level info popup. Going below the smallest value of something clears it, so the game's usual is used
+fn update_level_info(meta: &mut LevelMeta, step: impl Fn(Id) -> i32) {
+    // Par time goes up in 5 seconds
+    let par_time = meta.par_time.map_or(0, |t| t as i32) / 5 * 5 + step(400) * 5;
+    meta.par_time = Some(par_time.clamp(0, 99*60 + 55) as f32).filter(|t| *t > 0.0);
+    let par_score = meta.par_score.map_or(0, |s| s as i32) / 50 * 50 + step(402) * 50;
+    meta.par_score = Some(par_score.clamp(0, 99950) as usize).filter(|s| *s > 0);
+    // Ball speed in tenths, 1.0 is the same as not having one
+    let ball_speed = meta.ball_speed.map_or(10, |s| (s * 10.0).round() as i32) + step(404);
+    meta.ball_speed = Some(ball_speed.clamp(5, 20)).filter(|s| *s != 10).map(|s| s as f32 / 10.0);
+    // Backgrounds wrap around, going through the normal one on the way
+    let background = meta.background.and_then(|b| BACKGROUNDS.iter().position(|c| *c == b)).map_or(0, |i| i as i32 + 1);
+    let background = (background + step(406)).rem_euclid(BACKGROUNDS.len() as i32 + 1);
+    if step(406) != 0 {
+        meta.background = background.checked_sub(1).and_then(|i| BACKGROUNDS.get(i as usize)).copied();
+    }
+}
+
+// The -/+ buttons in the row motion popup. The distance etc. are kept when it stops moving, so they're still there if it starts again
+fn update_row_motion(motion: &mut RowMotion, step: impl Fn(Id) -> i32) {
+    let axes = [MotionAxis::None, MotionAxis::Horizontal, MotionAxis::Vertical];
+    let axis = axes.iter().position(|a| *a == motion.axis).unwrap_or(0) as i32 + step(420);
+    motion.axis = axes[axis.rem_euclid(axes.len() as i32) as usize];
+    motion.distance = (motion.distance as i32 + step(422) * 2).clamp(2, 48) as u8;
+    // Period goes in half seconds, phase in quarters
+    let period = (motion.period * 2.0).round() as i32 + step(424);
+    motion.period = period.clamp(1, 20) as f32 / 2.0;
+    let phase = (motion.phase * 4.0).round() as i32 + step(426);
+    motion.phase = phase.rem_euclid(4) as f32 / 4.0;
+}
+
+// Random powerups don't have a sprite, so they're a dark box with a question mark
+// Where a button in the palette along the bottom goes, the same size as a tile
+fn palette_slot_rect(slot: usize) -> Rect {
+    Rect::new(slot as f32 * (TILE_WIDTH + 1.0), Level::view_size().y - (TILE_HEIGHT + 1.0), TILE_WIDTH + 1.0, TILE_HEIGHT + 1.0)
+}
+
+pub fn draw_placed_powerup(texture: &Texture2D, powerup: PlacedPowerup, pos: Vec2, color: Color) {
+    match powerup {
+        PlacedPowerup::None => {}
+        PlacedPowerup::Random => {
+            draw_rectangle(pos.x, pos.y, 13.0, 7.0, Color::new(0.0, 0.0, 0.0, 0.6 * color.a));
+            render_text("?", pos + vec2(4.0, 0.0), color, TextAlign::Left, texture);
+        }
+        PlacedPowerup::Kind(kind) => draw_texture_ex(texture, pos.x, pos.y, color, DrawTextureParams {
+            source: Some(kind.texture_rect()),
+            ..Default::default()
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Every tile and powerup has a button on exactly one page, and none of a page's buttons are off screen or on top of each other
+    #[test]
+    fn palette_pages_fit() {
+        let mut editor_gui = EditorGui::new();
+        let palette_ids: Vec<Id> = (0..TILES_BUTTONS.len() as Id).chain((0..POWERUP_BUTTONS.len() as Id).map(|i| POWERUP_BUTTONS_ID + i)).collect();
+        let mut pages_shown = vec![0; palette_ids.len()];
+
+        for page in 0..EditorGui::palette_pages() {
+            editor_gui.palette_page = page;
+            editor_gui.insert_palette_buttons();
+
+            let mut rects = vec![editor_gui.gui.button(108).unwrap().rect()];
+            for (i, id) in palette_ids.iter().enumerate() {
+                if let Some(button) = editor_gui.gui.button(*id) {
+                    rects.push(button.rect());
+                    pages_shown[i] += 1;
+                }
+            }
+            for (i, a) in rects.iter().enumerate() {
+                assert!(a.x >= 0.0 && a.right() <= Level::view_size().x, "page {page} has a button at {a:?}");
+                for b in &rects[i + 1..] {
+                    assert!(a.right() <= b.x || b.right() <= a.x, "page {page} has {a:?} over {b:?}");
+                }
             }
         }
+        assert!(pages_shown.iter().all(|p| *p == 1), "{pages_shown:?}");
+
+        let mut every_kind = (0..).map_while(|i| PowerupKind::try_from(i).ok());
+        assert!(every_kind.all(|k| POWERUP_BUTTONS.contains(&PlacedPowerup::Kind(k))));
     }
-}
\ No newline at end of file
+}
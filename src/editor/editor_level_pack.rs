@@ -1,6 +1,6 @@
 use std::{fs, io::Write};
 
-use crate::game::{level_pack::{LevelPack, MAX_LEVELS}, world::level::{Level, LEVEL_NAME_LEN}};
+use crate::game::{bytes::ByteWriter, level_pack::{LevelPack, CHUNK_END, CHUNK_HIT_POINTS, CHUNK_META, CHUNK_MOTION, CHUNK_POWERUPS, CHUNK_SIZE, CHUNK_TILES, MAX_LEVELS, PACK_MAGIC, PACK_VERSION}, world::{level::{Level, LevelMeta, RowMotion, LEVEL_HEIGHT, LEVEL_NAME_LEN, LEVEL_WIDTH}, powerup::PlacedPowerup}};
 
 use super::timewarp::Timewarp;
 
@@ -41,6 +41,12 @@ pub struct EditorLevelPack {
     author: String,
 }
 
+impl Default for EditorLevelPack {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl EditorLevelPack {
     pub fn new() -> Self {
         Self {
@@ -94,6 +100,11 @@ impl EditorLevelPack {
         self.levels[self.current].push_current_state();
     }
 
+    // The first level with a portal that has nothing to pair with, packs can't be saved until they're all sorted out
+    pub fn unpaired_portals(&self) -> Option<usize> {
+        self.levels.iter().position(|l| !l.level.portals_paired())
+    }
+
     pub fn can_add(&self) -> bool {
         self.levels.len() < MAX_LEVELS
     }
@@ -161,19 +172,27 @@ impl EditorLevelPack {
         save_desktop(bytes, self.name());
     }
 
+    // Packs are always saved in the newest format, see level_pack.rs for what it looks like
     pub fn encode_to_file(&self) -> Vec<u8> {
-        let mut data: Vec<u8> = Vec::new();
+        let mut data = ByteWriter::new();
 
         // Turns a string into a bunch of bytes 'LEVEL_NAME_LEN' long.
         // If the name is shorter than LEVEL_NAME_LEN, 0xFF will fill the rest of the space. (0xFF doesn't correspond to any allowed characters) 
-        let push_string_bytes = |data: &mut Vec<u8>, string: &String| {
+        let push_string_bytes = |data: &mut ByteWriter, string: &String| {
             let bytes = string.as_bytes();
             for i in 0..LEVEL_NAME_LEN {
-                data.push(*bytes.get(i).unwrap_or(&0xFF));
+                data.u8(*bytes.get(i).unwrap_or(&0xFF));
             }
         };
+        let push_chunk = |data: &mut ByteWriter, tag: u8, chunk: &[u8]| {
+            data.u8(tag);
+            data.usize(chunk.len());
+            data.bytes(chunk);
+        };
 
-        // The file begins with the pack name and author
+        // The file begins with the header, then the pack name and author
+        data.bytes(&PACK_MAGIC);
+        data.u8(PACK_VERSION);
         push_string_bytes(&mut data, self.name());
         push_string_bytes(&mut data, self.author());
 
@@ -181,16 +200,37 @@ impl EditorLevelPack {
         for level in self.levels.iter().map(|el| &el.level) {
             // First we add the name...
             push_string_bytes(&mut data, level.name());
-            // And then the tiles!
-            // Each set of two tiles are encoded as a single byte.
-            // Since there are only 15 tiles two tiles are guaranteed to fit in a byte.
-            for t in level.tiles().chunks_exact(2) {
-                let (a, b) = (t[0] as u8, t[1] as u8);
-                data.push((a << 4) + b);
+            // And then the chunks!
+            if (level.width(), level.height()) != (LEVEL_WIDTH, LEVEL_HEIGHT) {
+                push_chunk(&mut data, CHUNK_SIZE, &[level.width() as u8, level.height() as u8]);
+            }
+            let tiles: Vec<u8> = level.tiles().iter().map(|t| *t as u8).collect();
+            push_chunk(&mut data, CHUNK_TILES, &tiles);
+            if level.hit_points().iter().any(|hp| *hp != 0) {
+                push_chunk(&mut data, CHUNK_HIT_POINTS, level.hit_points());
+            }
+            if level.powerups().iter().any(|p| *p != PlacedPowerup::None) {
+                let powerups: Vec<u8> = level.powerups().iter().map(|p| (*p).into()).collect();
+                push_chunk(&mut data, CHUNK_POWERUPS, &powerups);
+            }
+            if *level.meta() != LevelMeta::default() {
+                let mut meta = ByteWriter::new();
+                level.meta().write(&mut meta);
+                push_chunk(&mut data, CHUNK_META, &meta.into_bytes());
+            }
+            let motions: Vec<(usize, &RowMotion)> = level.motions().iter().enumerate().filter(|(_, m)| m.moving()).collect();
+            if !motions.is_empty() {
+                let mut motion = ByteWriter::new();
+                motion.list(&motions, |w, (row, m)| {
+                    w.u8(*row as u8);
+                    m.write(w);
+                });
+                push_chunk(&mut data, CHUNK_MOTION, &motion.into_bytes());
             }
+            data.u8(CHUNK_END);
         }
 
-        data
+        data.into_bytes()
     }
 }
 
@@ -219,6 +259,7 @@ fn save_desktop(bytes: Vec<u8>, name: &String) {
     let mut file = fs::OpenOptions::new()
         .create(true)
         .write(true)
+        .truncate(true)
         .open(format!("{}.brk", name))
         .unwrap();
     file.write_all(&bytes).unwrap();
@@ -1,8 +1,8 @@
-use editor_gui::EditorGui;
+use editor_gui::{draw_placed_powerup, EditorGui};
 use editor_level_pack::EditorLevelPack;
-use macroquad::{color::WHITE, input::{clear_input_queue, is_key_pressed, is_mouse_button_pressed, is_mouse_button_released, MouseButton}, math::{vec2, Rect, Vec2}, shapes::draw_line, texture::Texture2D, window::clear_background};
+use macroquad::{color::{Color, WHITE}, input::{clear_input_queue, is_key_pressed, is_mouse_button_pressed, is_mouse_button_released, KeyCode, MouseButton}, math::{vec2, Vec2}, shapes::{draw_line, draw_rectangle_lines}, texture::Texture2D, window::clear_background};
 
-use crate::{game::{level_pack::LevelPack, world::{level::{Level, Tile, LEVEL_HEIGHT, LEVEL_HEIGHT_PADDING_TOP, LEVEL_WIDTH, TILE_GAP, TILE_HEIGHT, TILE_WIDTH}, Lives, World, BG_COL}, KEY_PAUSE}, gui::GRID_COL, text_renderer::{render_text, TextAlign}, Scene, SceneChange};
+use crate::{game::{input::KeyboardInput, level_pack::LevelPack, timestep::FixedTimestep, world::{level::{Level, Tile, LEVEL_HEIGHT, LEVEL_WIDTH, TILE_GAP, TILE_HEIGHT, TILE_WIDTH}, powerup::PlacedPowerup, rules::GameRules, Lives, World, BG_COL}, KEY_PAUSE}, gui::GRID_COL, text_renderer::{render_text, TextAlign}, Scene, SceneChange};
 
 pub mod editor_gui;
 pub mod editor_level_pack;
@@ -17,12 +17,18 @@ pub struct Editor {
     gui: EditorGui,
 
     draw_type: Tile,
+    draw_hit_points: u8,
+    draw_powerup: PlacedPowerup,
     click_action: ClickAction,
 
     level_pack: EditorLevelPack,
 
     world: Option<World>,
+    world_timestep: FixedTimestep,
+    world_input: KeyboardInput,
     paddle_pos: Option<f32>,
+    // The time moving rows are shown at, the level itself always stays at 0 so tiles line up with the grid
+    preview_time: f32,
 }
 
 impl Default for Editor {
@@ -31,12 +37,17 @@ impl Default for Editor {
             gui: EditorGui::new(),
 
             draw_type: Tile::Red,
+            draw_hit_points: 0,
+            draw_powerup: PlacedPowerup::Random,
             click_action: ClickAction::None,
             
             level_pack: EditorLevelPack::new(),
 
             world: None,
+            world_timestep: FixedTimestep::new(),
+            world_input: KeyboardInput::new(),
             paddle_pos: None,
+            preview_time: 0.0,
         }
     }
 }
@@ -58,24 +69,36 @@ impl Scene for Editor {
                 self.world = None;
             } else {
                 self.gui.stop_editing_name();
-                self.world = Some(World::new(self.level_pack.level().clone(), None, self.paddle_pos, Lives::Infinite, None));
+                self.world = Some(World::new(self.level_pack.level().clone(), self.level_pack.current(), None, self.paddle_pos, Lives::Infinite, None, macroquad::rand::rand() as u64, GameRules::default()));
+                self.world_timestep = FixedTimestep::new();
+                self.world_input = KeyboardInput::new();
             }
             clear_input_queue();
         }
 
         if let Some(world) = &mut self.world {
-            world.update();
+            self.world_input.poll();
+            for _ in 0..self.world_timestep.advance(macroquad::time::get_frame_time()) {
+                world.update(self.world_input.take());
+            }
             return None;
         }
 
+        self.preview_time += macroquad::time::get_frame_time();
+
         // Gui
-        self.gui.update(mouse_pos, &mut self.level_pack, &mut self.draw_type);
+        self.gui.update(mouse_pos, &mut self.level_pack, &mut self.draw_type, &mut self.draw_hit_points, &mut self.draw_powerup);
 
         // Clear
-        if self.gui.button_clear() && *self.level_pack.level().tiles() != [Tile::Air; LEVEL_WIDTH*LEVEL_HEIGHT] {
+        let level_empty = *self.level_pack.level().tiles() == [Tile::Air; LEVEL_WIDTH*LEVEL_HEIGHT] && self.level_pack.level().powerups().iter().all(|p| *p == PlacedPowerup::None);
+        if self.gui.button_clear() && !level_empty {
             self.level_pack.timewarp_save_previous_state();
             self.level_pack.timewarp_push_current_state();
+            // The level's info isn't part of the undo history, so it's kept, and so is its size
+            let (meta, width, height) = (self.level_pack.level().meta().clone(), self.level_pack.level().width(), self.level_pack.level().height());
             *self.level_pack.level_mut() = Level::new();
+            *self.level_pack.level_mut().meta_mut() = meta;
+            self.level_pack.level_mut().set_size(width, height);
         }
 
         // Exit / Save
@@ -114,15 +137,34 @@ impl Scene for Editor {
             self.level_pack.delete_level();
         }
 
+        // Resizing
+        if !self.gui.editing_text() && !self.gui.popup_open() {
+            let (width, height) = (self.level_pack.level().width(), self.level_pack.level().height());
+            for (key, (new_width, new_height)) in [
+                (KeyCode::Left,  (width - 1, height)),
+                (KeyCode::Right, (width + 1, height)),
+                (KeyCode::Up,    (width, height - 1)),
+                (KeyCode::Down,  (width, height + 1)),
+            ] {
+                let new_size = (new_width.clamp(1, LEVEL_WIDTH), new_height.clamp(1, LEVEL_HEIGHT));
+                if is_key_pressed(key) && new_size != (width, height) {
+                    self.level_pack.timewarp_save_previous_state();
+                    self.level_pack.level_mut().set_size(new_size.0, new_size.1);
+                    self.level_pack.timewarp_push_current_state();
+                }
+            }
+        }
+
         // Editing tiles
-        let level_area_rect = Rect::new(0.0, LEVEL_HEIGHT_PADDING_TOP as f32 * (TILE_HEIGHT + TILE_GAP), LEVEL_WIDTH as f32 * (TILE_WIDTH + TILE_GAP), LEVEL_HEIGHT as f32 * (TILE_HEIGHT + TILE_GAP) - TILE_GAP);
+        let level_area_rect = self.level_pack.level().area_rect();
 
         if level_area_rect.contains(mouse_pos) && !self.gui.popup_open() {
-            let hovered_tile_pos = ((mouse_pos - vec2(0.0, LEVEL_HEIGHT_PADDING_TOP as f32 * (TILE_HEIGHT * TILE_GAP) + TILE_GAP)) / (vec2(TILE_WIDTH, TILE_HEIGHT) + TILE_GAP)).floor();
-            let hovered_tile_index = match hovered_tile_pos.x >= 0.0 && hovered_tile_pos.x < LEVEL_WIDTH as f32 && hovered_tile_pos.y >= 0.0 && hovered_tile_pos.y < LEVEL_HEIGHT as f32 {
-                false => None,
-                true => Some(hovered_tile_pos.y as usize * LEVEL_WIDTH + hovered_tile_pos.x as usize),
-            };
+            let hovered_tile_index = self.level_pack.level().tile_index_at(mouse_pos);
+
+            // Making the hovered row move
+            if let (Some(tile_index), true, false) = (hovered_tile_index, is_key_pressed(KeyCode::M), self.gui.editing_text()) {
+                self.gui.open_row_motion(tile_index / LEVEL_WIDTH);
+            }
     
             for (mouse_button, click_action) in [
                 (MouseButton::Left, ClickAction::Draw),
@@ -138,14 +180,35 @@ impl Scene for Editor {
                 }
             }
             
-            if let Some(tile_index) = hovered_tile_index {
+            // Placing powerups under tiles
+            if let (Some(tile_index), true) = (hovered_tile_index, self.gui.placing_powerups()) {
+                let powerup = match self.click_action {
+                    ClickAction::Draw  => Some(self.draw_powerup),
+                    ClickAction::Erase => Some(PlacedPowerup::None),
+                    _ => None
+                };
+                if let (Some(powerup), Some(p)) = (powerup, self.level_pack.level_mut().powerups_mut().get_mut(tile_index)) {
+                    *p = powerup;
+                }
+            }
+            // Or the tiles themselves
+            else if let Some(tile_index) = hovered_tile_index {
                 let tile_type = match self.click_action {
                     ClickAction::Draw  => Some(self.draw_type),
                     ClickAction::Erase => Some(Tile::Air),
                     _ => None
                 };
                 if let Some(tile_type) = tile_type {
-                    self.level_pack.level_mut().tiles_mut().get_mut(tile_index).map(|t| *t = tile_type);
+                    let hit_points = if tile_type != Tile::Air { self.draw_hit_points } else { 0 };
+                    let level = self.level_pack.level_mut();
+                    if let Some(t) = level.tiles_mut().get_mut(tile_index) {
+                        *t = tile_type;
+                        level.hit_points_mut()[tile_index] = hit_points;
+                        // Nothing can drop from an empty cell
+                        if tile_type == Tile::Air {
+                            level.powerups_mut()[tile_index] = PlacedPowerup::None;
+                        }
+                    }
                 }
             }
         } else {
@@ -160,27 +223,49 @@ impl Scene for Editor {
 
     fn draw(&self, texture: &Texture2D, _: Option<(&String, &String)>) {
         if let Some(world) = &self.world {
-            world.draw(texture);
+            world.draw(texture, self.world_timestep.alpha());
             render_text(&String::from("PRESS ESC TO RETURN TO EDITOR."), vec2(0.0, 7.0), WHITE, TextAlign::Left, texture);
             return;
         }
 
-        clear_background(BG_COL);
+        let level = self.level_pack.level();
+        clear_background(level.meta().background_color().unwrap_or(BG_COL));
+
+        // Grid, only as big as the level
+        let (origin, cell) = (level.origin(), vec2(TILE_WIDTH, TILE_HEIGHT) + TILE_GAP);
+        let size = vec2(level.width() as f32, level.height() as f32) * cell;
+        for x in 0..=level.width() {
+            let x = origin.x + x as f32 * cell.x - 0.5;
+            draw_line(x, origin.y, x, origin.y + size.y, 1.0, GRID_COL);
+        }
+        for y in 0..=level.height() {
+            let y = origin.y + y as f32 * cell.y - 0.5;
+            draw_line(origin.x - 0.5, y, origin.x + size.x - 0.5, y, 1.0, GRID_COL);
+        }
+        level.draw(texture);
 
-        let view_size = Level::view_size();
+        // Where the moving rows are right now, faded over the top
+        if level.moving() {
+            let mut preview = level.clone();
+            preview.set_time(self.preview_time);
+            preview.draw_moving(texture, Color::new(1.0, 1.0, 1.0, 0.4));
+        }
 
-        // Grid
-        for x in 1..=LEVEL_WIDTH {
-            let x = x as f32 * (TILE_WIDTH + TILE_GAP) - 0.5;
-            draw_line(x, LEVEL_HEIGHT_PADDING_TOP as f32 * (TILE_HEIGHT + TILE_GAP), x, (LEVEL_HEIGHT_PADDING_TOP + LEVEL_HEIGHT) as f32  * (TILE_HEIGHT + TILE_GAP), 1.0, GRID_COL);
+        // Portals with nothing to pair with are outlined, since the pack can't be saved with them
+        for (i, t) in level.tiles().iter().enumerate() {
+            if matches!(t, Tile::PortalA | Tile::PortalB) && level.portal_partner(i).is_none() {
+                let rect = level.tile_rect(i);
+                draw_rectangle_lines(rect.x - 1.0, rect.y - 1.0, rect.w + 2.0, rect.h + 2.0, 2.0, Color::from_rgba(255, 60, 60, 255));
+            }
         }
-        for y in 0..=LEVEL_HEIGHT {
-            let y = (y + LEVEL_HEIGHT_PADDING_TOP) as f32 * (TILE_HEIGHT + TILE_GAP) - 0.5;
-            draw_line(0.0, y, view_size.x, y, 1.0, GRID_COL);
+
+        // Placed powerups, faded unless they're being edited
+        let powerup_col = Color::new(1.0, 1.0, 1.0, if self.gui.placing_powerups() { 1.0 } else { 0.4 });
+        for (i, p) in self.level_pack.level().powerups().iter().enumerate() {
+            draw_placed_powerup(texture, *p, self.level_pack.level().tile_pos(i) - 1.0, powerup_col);
         }
-        self.level_pack.level().draw(texture);
 
         // Gui
-        self.gui.draw(texture, &self.level_pack, &self.draw_type);
+        self.gui.draw(texture, &self.level_pack, &self.draw_type, self.draw_hit_points, &self.draw_powerup);
     }
 }
\ No newline at end of file
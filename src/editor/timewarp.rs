@@ -1,17 +1,18 @@
 use std::collections::VecDeque;
 
-use crate::game::world::level::{Level, TileArray};
+use crate::game::world::level::Level;
 
+// Keeps whole levels so anything about a tile (hit points etc.) can be undone, but the name and settings are left alone
 pub struct Timewarp {
-    previous_state: TileArray,
-    undo_states: VecDeque<TileArray>,
-    redo_states: Vec<TileArray>,
+    previous_state: Level,
+    undo_states: VecDeque<Level>,
+    redo_states: Vec<Level>,
 }
 
 impl Timewarp {
     pub fn new(level: &Level) -> Self {
         Self {
-            previous_state: level.tiles().clone(),
+            previous_state: level.clone(),
             undo_states: VecDeque::with_capacity(50),
             redo_states: Vec::with_capacity(50)
         }
@@ -26,25 +27,31 @@ impl Timewarp {
 
     pub fn undo(&mut self, level: &mut Level) {
         if let Some(undo_state) = self.undo_states.pop_front() {
-            self.redo_states.push(level.tiles().clone());
-            *level.tiles_mut() = undo_state;
+            self.redo_states.push(level.clone());
+            restore(level, undo_state);
         }
     }
     pub fn redo(&mut self, level: &mut Level) {
         if let Some(redo_state) = self.redo_states.pop() {
-            self.undo_states.push_front(level.tiles().clone());
-            *level.tiles_mut() = redo_state;
+            self.undo_states.push_front(level.clone());
+            restore(level, redo_state);
         }
     }
 
     pub fn save_previous_state(&mut self, level: &Level) {
-        if self.previous_state == *level.tiles() {
+        if self.previous_state == *level {
             return;
         }
-        self.previous_state = level.tiles().clone();
+        self.previous_state = level.clone();
     }
     pub fn push_current_state(&mut self) {
-        self.undo_states.push_front(self.previous_state);
+        self.undo_states.push_front(self.previous_state.clone());
         self.redo_states.clear();
     }
-}
\ No newline at end of file
+}
+
+fn restore(level: &mut Level, mut state: Level) {
+    *state.name_mut() = level.name().clone();
+    *state.meta_mut() = level.meta().clone();
+    *level = state;
+}
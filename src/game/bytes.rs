@@ -0,0 +1,129 @@
+use macroquad::math::{vec2, Vec2};
+
+// Helpers for the little binary formats the game writes (saved sessions etc).
+// Everything is little endian, and lengths of lists are written as u32s before them
+
+#[derive(Default)]
+pub struct ByteWriter {
+    data: Vec<u8>,
+}
+
+impl ByteWriter {
+    pub fn new() -> Self {
+        Self { data: Vec::new() }
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.data
+    }
+
+    pub fn bytes(&mut self, bytes: &[u8]) {
+        self.data.extend_from_slice(bytes);
+    }
+    pub fn u8(&mut self, v: u8) {
+        self.data.push(v);
+    }
+    pub fn bool(&mut self, v: bool) {
+        self.data.push(v as u8);
+    }
+    pub fn u32(&mut self, v: u32) {
+        self.bytes(&v.to_le_bytes());
+    }
+    pub fn u64(&mut self, v: u64) {
+        self.bytes(&v.to_le_bytes());
+    }
+    pub fn usize(&mut self, v: usize) {
+        self.u32(v as u32);
+    }
+    pub fn f32(&mut self, v: f32) {
+        self.bytes(&v.to_le_bytes());
+    }
+    pub fn vec2(&mut self, v: Vec2) {
+        self.f32(v.x);
+        self.f32(v.y);
+    }
+    pub fn option<T>(&mut self, v: Option<T>, write: impl FnOnce(&mut Self, T)) {
+        self.bool(v.is_some());
+        if let Some(v) = v {
+            write(self, v);
+        }
+    }
+    pub fn list<T>(&mut self, list: &[T], mut write: impl FnMut(&mut Self, &T)) {
+        self.usize(list.len());
+        for v in list {
+            write(self, v);
+        }
+    }
+}
+
+// Reads values back in the same order they were written. Everything returns None if there aren't enough bytes left
+pub struct ByteReader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data, offset: 0 }
+    }
+
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+    pub fn remaining(&self) -> usize {
+        self.data.len().saturating_sub(self.offset)
+    }
+    pub fn finished(&self) -> bool {
+        self.offset >= self.data.len()
+    }
+
+    pub fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        let bytes = self.data.get(self.offset..self.offset.checked_add(len)?)?;
+        self.offset += len;
+        Some(bytes)
+    }
+    pub fn array<const N: usize>(&mut self) -> Option<[u8; N]> {
+        self.bytes(N)?.try_into().ok()
+    }
+    pub fn u8(&mut self) -> Option<u8> {
+        Some(self.array::<1>()?[0])
+    }
+    pub fn bool(&mut self) -> Option<bool> {
+        match self.u8()? {
+            0 => Some(false),
+            1 => Some(true),
+            _ => None,
+        }
+    }
+    pub fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_le_bytes(self.array()?))
+    }
+    pub fn u64(&mut self) -> Option<u64> {
+        Some(u64::from_le_bytes(self.array()?))
+    }
+    pub fn usize(&mut self) -> Option<usize> {
+        Some(self.u32()? as usize)
+    }
+    pub fn f32(&mut self) -> Option<f32> {
+        Some(f32::from_le_bytes(self.array()?))
+    }
+    pub fn vec2(&mut self) -> Option<Vec2> {
+        Some(vec2(self.f32()?, self.f32()?))
+    }
+    pub fn option<T>(&mut self, read: impl FnOnce(&mut Self) -> Option<T>) -> Option<Option<T>> {
+        match self.bool()? {
+            true => Some(Some(read(self)?)),
+            false => Some(None),
+        }
+    }
+    // Reads a u32 length then that many things
+    pub fn list<T>(&mut self, mut read: impl FnMut(&mut Self) -> Option<T>) -> Option<Vec<T>> {
+        let len = self.usize()?;
+        // Don't trust the length enough to allocate it all up front, a broken file could say anything
+        let mut list = Vec::with_capacity(len.min(1024));
+        for _ in 0..len {
+            list.push(read(self)?);
+        }
+        Some(list)
+    }
+}
@@ -0,0 +1,47 @@
+use macroquad::input::{is_key_down, is_key_pressed, is_key_released, KeyCode};
+
+use super::world::input::InputFrame;
+
+pub const KEY_CARRY: KeyCode = KeyCode::Space;
+
+// Builds an InputFrame from the keyboard.
+// Presses and releases are held onto until a tick takes them, so they aren't lost when a frame runs no ticks, or repeated when it runs several
+#[derive(Default)]
+pub struct KeyboardInput {
+    pending: InputFrame,
+}
+
+impl KeyboardInput {
+    pub fn new() -> Self {
+        Self { pending: InputFrame::default() }
+    }
+
+    // Should be called once per frame, before any ticks are run
+    pub fn poll(&mut self) {
+        let mut move_axis = 0.0;
+        if is_key_down(KeyCode::A) || is_key_down(KeyCode::Left) {
+            move_axis -= 1.0;
+        }
+        if is_key_down(KeyCode::D) || is_key_down(KeyCode::Right) {
+            move_axis += 1.0;
+        }
+
+        self.pending = InputFrame {
+            move_axis,
+            fire: is_key_down(KeyCode::W) || is_key_down(KeyCode::Up),
+            carry_held: is_key_down(KEY_CARRY),
+            carry_released:   self.pending.carry_released   || is_key_released(KEY_CARRY),
+            test_balls_left:  self.pending.test_balls_left  || is_key_pressed(KeyCode::Q),
+            test_balls_right: self.pending.test_balls_right || is_key_pressed(KeyCode::E),
+        };
+    }
+
+    // The input for the next tick
+    pub fn take(&mut self) -> InputFrame {
+        let input = self.pending;
+        self.pending.carry_released   = false;
+        self.pending.test_balls_left  = false;
+        self.pending.test_balls_right = false;
+        input
+    }
+}
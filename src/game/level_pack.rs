@@ -1,9 +1,79 @@
 use crate::text_renderer::char_valid;
 
-use super::world::level::{Level, Tile, LEVEL_HEIGHT, LEVEL_NAME_LEN, LEVEL_WIDTH};
+use super::{bytes::ByteReader, world::{level::{Level, LevelMeta, RowMotion, Tile, LEVEL_HEIGHT, LEVEL_NAME_LEN, LEVEL_WIDTH, MAX_HIT_POINTS}, powerup::PlacedPowerup}};
 
 pub const MAX_LEVELS: usize = 99;
 
+// v1 packs have no header and start straight away with the pack name, whose bytes are never 0, so a 0 at the start means it's v2 or later.
+// v2 packs look like this:
+//   PACK_MAGIC, version (u8), pack name, pack author
+//   then for each level, its name and a list of chunks, ending with CHUNK_END. Each chunk is a tag (u8), a length (u32) and that many bytes.
+// New things a level needs saving should be added as new chunks rather than changing the version, unknown chunks are skipped when loading
+pub const PACK_MAGIC: [u8; 4] = [0x00, b'B', b'R', b'K'];
+pub const PACK_VERSION: u8 = 2;
+
+pub const CHUNK_END: u8 = 0;
+// One byte for each tile, in reading order. Portals pair up in that order too, see 'Level::portal_partner'
+pub const CHUNK_TILES: u8 = 1;
+// One byte for each tile's hit points, 0 for however many its material has. Only saved if a tile has them set
+pub const CHUNK_HIT_POINTS: u8 = 2;
+// One byte for the powerup placed under each tile, only saved if there are any. See 'PlacedPowerup' for the values
+pub const CHUNK_POWERUPS: u8 = 3;
+// The level's width and height as a byte each, only saved if it's smaller than the most. Tile chunks are always full size
+pub const CHUNK_SIZE: u8 = 4;
+// Par time, par score, ball speed, background and hint, see 'LevelMeta::write'. Only saved if any of them are set
+pub const CHUNK_META: u8 = 5;
+// Each moving row, as a list (u32 count) of its row (u8) and its motion, see 'RowMotion::write'. Only saved if any rows move
+pub const CHUNK_MOTION: u8 = 6;
+
+// Why a pack couldn't be loaded. Offsets are in bytes from the start of the file
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum PackLoadError {
+    // The file ends partway through something
+    UnexpectedEnd { offset: usize },
+    UnsupportedVersion { version: u8 },
+    // A v1 pack with bytes left over that aren't a whole level
+    TrailingBytes { offset: usize, len: usize },
+    InvalidCharacter { offset: usize, byte: u8 },
+    InvalidTile { offset: usize, value: u8 },
+    InvalidHitPoints { offset: usize, value: u8 },
+    InvalidPowerup { offset: usize, value: u8 },
+    InvalidSize { offset: usize, width: usize, height: usize },
+    // The metadata chunk couldn't be read, or had something silly in it
+    InvalidMeta { offset: usize },
+    // The row motion chunk couldn't be read, or a row was outside of the level
+    InvalidMotion { offset: usize },
+    // Any other chunk that's the wrong size
+    WrongChunkLength { offset: usize, len: usize },
+    // A level's tiles, hit points or powerups chunk is the wrong size
+    WrongTileCount { offset: usize, count: usize },
+    NoLevels,
+    TooManyLevels { count: usize },
+}
+
+impl std::fmt::Display for PackLoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnexpectedEnd { offset }           => write!(f, "file ends early at byte {}", offset),
+            Self::UnsupportedVersion { version }     => write!(f, "unsupported version {}", version),
+            Self::TrailingBytes { offset, len }      => write!(f, "{} extra bytes at byte {}", len, offset),
+            Self::InvalidCharacter { offset, byte }  => write!(f, "bad character {} at byte {}", byte, offset),
+            Self::InvalidTile { offset, value }      => write!(f, "bad tile {} at byte {}", value, offset),
+            Self::InvalidHitPoints { offset, value } => write!(f, "bad hit points {} at byte {}", value, offset),
+            Self::InvalidPowerup { offset, value }   => write!(f, "bad powerup {} at byte {}", value, offset),
+            Self::InvalidSize { offset, width, height } => write!(f, "bad size {}X{} at byte {}", width, height, offset),
+            Self::InvalidMeta { offset }             => write!(f, "bad level info at byte {}", offset),
+            Self::InvalidMotion { offset }           => write!(f, "bad row motion at byte {}", offset),
+            Self::WrongChunkLength { offset, len }   => write!(f, "bad chunk length {} at byte {}", len, offset),
+            Self::WrongTileCount { offset, count }   => write!(f, "{} tiles at byte {}, not {}", count, offset, LEVEL_WIDTH * LEVEL_HEIGHT),
+            Self::NoLevels                           => write!(f, "pack has no levels"),
+            Self::TooManyLevels { count }            => write!(f, "{} levels, the most is {}", count, MAX_LEVELS),
+        }
+    }
+}
+
+impl std::error::Error for PackLoadError {}
+
 #[derive(Clone)]
 pub struct LevelPack {
     name: String,
@@ -22,51 +92,304 @@ impl LevelPack {
         &self.levels
     }
 
-    pub fn load_from_file(data: Vec<u8>) -> Option<Self> {
-        // If the pack doesn't have enough bytes for the name and author, it's not valid
-        if data.len() <= LEVEL_NAME_LEN * 2 {
-            return None;
+    pub fn load_from_file(data: Vec<u8>) -> Result<Self, PackLoadError> {
+        let mut r = ByteReader::new(&data);
+        let (name, author, levels) = match data.starts_with(&PACK_MAGIC) {
+            true  => load_v2(&mut r)?,
+            false => load_v1(&mut r)?,
+        };
+
+        // If the pack has no levels, or too many levels, it's not valid!!
+        if levels.is_empty() {
+            return Err(PackLoadError::NoLevels);
+        }
+        if levels.len() > MAX_LEVELS {
+            return Err(PackLoadError::TooManyLevels { count: levels.len() });
+        }
+
+        Ok(LevelPack { name, author, levels })
+    }
+}
+
+// Gets 'len' bytes, or says where the file ended if there aren't enough
+fn take<'a>(r: &mut ByteReader<'a>, len: usize) -> Result<&'a [u8], PackLoadError> {
+    let offset = r.offset();
+    r.bytes(len).ok_or(PackLoadError::UnexpectedEnd { offset })
+}
+
+// Reads a string 'LEVEL_NAME_LEN' bytes long, which ends early at the first 0xFF
+fn load_string(r: &mut ByteReader) -> Result<String, PackLoadError> {
+    let offset = r.offset();
+    let mut string = String::new();
+    for (i, byte) in take(r, LEVEL_NAME_LEN)?.iter().enumerate() {
+        match *byte {
+            0xFF => break,
+            b if char_valid(b as char) => string.push(b as char),
+            b => return Err(PackLoadError::InvalidCharacter { offset: offset + i, byte: b }),
+        }
+    }
+    Ok(string)
+}
+
+fn load_tile(value: u8, offset: usize) -> Result<Tile, PackLoadError> {
+    Tile::try_from(value).map_err(|_| PackLoadError::InvalidTile { offset, value })
+}
+
+fn load_v2(r: &mut ByteReader) -> Result<(String, String, Vec<Level>), PackLoadError> {
+    take(r, PACK_MAGIC.len())?;
+    let version = take(r, 1)?[0];
+    if version != PACK_VERSION {
+        return Err(PackLoadError::UnsupportedVersion { version });
+    }
+
+    let name = load_string(r)?;
+    let author = load_string(r)?;
+
+    let mut levels = Vec::new();
+    while !r.finished() {
+        let mut level = Level::new();
+        *level.name_mut() = load_string(r)?;
+        // Only applied once all of the chunks are read, so it doesn't matter where the size chunk is
+        let mut size = (LEVEL_WIDTH, LEVEL_HEIGHT);
+
+        loop {
+            let tag = take(r, 1)?[0];
+            if tag == CHUNK_END {
+                break;
+            }
+            let len = u32::from_le_bytes(take(r, 4)?.try_into().unwrap()) as usize;
+            let offset = r.offset();
+            let chunk = take(r, len)?;
+
+            // Any other chunks are probably from a newer version of the game, so they're just skipped
+            if [CHUNK_TILES, CHUNK_HIT_POINTS, CHUNK_POWERUPS].contains(&tag) && chunk.len() != level.tiles().len() {
+                return Err(PackLoadError::WrongTileCount { offset, count: chunk.len() });
+            }
+            match tag {
+                CHUNK_TILES => {
+                    for (i, (t, byte)) in level.tiles_mut().iter_mut().zip(chunk).enumerate() {
+                        *t = load_tile(*byte, offset + i)?;
+                    }
+                }
+                CHUNK_HIT_POINTS => {
+                    for (i, (hp, byte)) in level.hit_points_mut().iter_mut().zip(chunk).enumerate() {
+                        if *byte > MAX_HIT_POINTS {
+                            return Err(PackLoadError::InvalidHitPoints { offset: offset + i, value: *byte });
+                        }
+                        *hp = *byte;
+                    }
+                }
+                CHUNK_SIZE => {
+                    let (width, height) = match chunk {
+                        [w, h] => (*w as usize, *h as usize),
+                        _ => return Err(PackLoadError::WrongChunkLength { offset, len: chunk.len() }),
+                    };
+                    if !(1..=LEVEL_WIDTH).contains(&width) || !(1..=LEVEL_HEIGHT).contains(&height) {
+                        return Err(PackLoadError::InvalidSize { offset, width, height });
+                    }
+                    size = (width, height);
+                }
+                CHUNK_POWERUPS => {
+                    for (i, (p, byte)) in level.powerups_mut().iter_mut().zip(chunk).enumerate() {
+                        *p = PlacedPowerup::try_from(*byte).map_err(|_| PackLoadError::InvalidPowerup { offset: offset + i, value: *byte })?;
+                    }
+                }
+                CHUNK_META => {
+                    let mut meta_reader = ByteReader::new(chunk);
+                    *level.meta_mut() = LevelMeta::read(&mut meta_reader)
+                        .filter(|_| meta_reader.finished())
+                        .ok_or(PackLoadError::InvalidMeta { offset })?;
+                }
+                CHUNK_MOTION => {
+                    let mut motion_reader = ByteReader::new(chunk);
+                    let rows = motion_reader.list(|r| Some((r.u8()? as usize, RowMotion::read(r)?)))
+                        .filter(|rows| motion_reader.finished() && rows.iter().all(|(row, _)| *row < LEVEL_HEIGHT))
+                        .ok_or(PackLoadError::InvalidMotion { offset })?;
+                    for (row, motion) in rows {
+                        level.motions_mut()[row] = motion;
+                    }
+                }
+                _ => {}
+            }
         }
-        
-        let load_string = |bytes: &[u8]| -> String {
-            let mut string = String::new();
-            for i in 0..LEVEL_NAME_LEN {
-                let character = match bytes.get(i) {
-                    Some(byte) if char_valid(*byte as char) => *byte as char,
-                    _ => break,
+        level.set_size(size.0, size.1);
+
+        levels.push(level);
+    }
+
+    Ok((name, author, levels))
+}
+
+// The original format, with no header and two tiles packed into each byte
+fn load_v1(r: &mut ByteReader) -> Result<(String, String, Vec<Level>), PackLoadError> {
+    // The file begins with the name and author of the level pack
+    let name = load_string(r)?;
+    let author = load_string(r)?;
+
+    // Then it holds all of the levels
+    let mut levels = Vec::new();
+    let level_len = LEVEL_NAME_LEN + (LEVEL_WIDTH * LEVEL_HEIGHT)/2;
+    while !r.finished() {
+        let offset = r.offset();
+        let remaining = r.remaining();
+        if remaining < level_len {
+            return Err(PackLoadError::TrailingBytes { offset, len: remaining });
+        }
+
+        let mut level = Level::new();
+        *level.name_mut() = load_string(r)?;
+
+        let offset = r.offset();
+        for (i, tiles_byte) in take(r, level_len - LEVEL_NAME_LEN)?.iter().enumerate() {
+            let (a, b) = (tiles_byte >> 4, tiles_byte & 0b1111);
+
+            for (index, nibble) in [(i*2, a), (i*2+1, b)] {
+                level.tiles_mut()[index] = load_tile(nibble, offset + i)?;
+            }
+        }
+
+        levels.push(level);
+    }
+
+    Ok((name, author, levels))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn string_bytes(string: &str) -> Vec<u8> {
+        (0..LEVEL_NAME_LEN).map(|i| *string.as_bytes().get(i).unwrap_or(&0xFF)).collect()
+    }
+
+    #[test]
+    fn load_v1() {
+        let mut data = [string_bytes("OLD PACK"), string_bytes("SOMEONE")].concat();
+        for name in ["FIRST", "SECOND"] {
+            data.extend(string_bytes(name));
+            data.extend((0..LEVEL_WIDTH * LEVEL_HEIGHT / 2).map(|i| ((i % 16) << 4 | (15 - i % 16)) as u8));
+        }
+
+        let pack = LevelPack::load_from_file(data.clone()).unwrap();
+        assert_eq!((pack.name().as_str(), pack.author().as_str()), ("OLD PACK", "SOMEONE"));
+        assert_eq!(pack.levels().iter().map(|l| l.name().as_str()).collect::<Vec<_>>(), ["FIRST", "SECOND"]);
+        for level in pack.levels() {
+            assert_eq!((level.width(), level.height()), (LEVEL_WIDTH, LEVEL_HEIGHT));
+            for (i, tile) in level.tiles().iter().enumerate() {
+                let nibble = match i % 2 {
+                    0 => i / 2 % 16,
+                    _ => 15 - i / 2 % 16,
                 };
-                string.push(character);
+                assert_eq!(*tile, Tile::try_from(nibble as u8).unwrap());
             }
-            string
-        };
+        }
 
-        // The file begins with the name and author of the level pack
-        let name = load_string(&data[0..LEVEL_NAME_LEN]);
-        let author = load_string(&data[LEVEL_NAME_LEN..LEVEL_NAME_LEN*2]);
+        data.push(0);
+        assert_eq!(LevelPack::load_from_file(data.clone()).err(), Some(PackLoadError::TrailingBytes { offset: data.len() - 1, len: 1 }));
+    }
 
-        // Then it holds all of the levels
-        let mut levels = Vec::new(); 
-        for level_bytes in data[LEVEL_NAME_LEN*2..].chunks_exact(LEVEL_NAME_LEN + (LEVEL_WIDTH * LEVEL_HEIGHT)/2) {
+    #[test]
+    fn load_bundled_packs() {
+        for data in [include_bytes!("../../ALBUMS I LIKE.brk").to_vec(), include_bytes!("../../SPACE.brk").to_vec()] {
+            assert!(LevelPack::load_from_file(data).is_ok());
+        }
+    }
+
+    // Packs are only saved by the editor
+    #[cfg(feature = "graphics")]
+    mod v2 {
+        use super::*;
+        use crate::{editor::editor_level_pack::EditorLevelPack, game::world::{level::{MotionAxis, TILE_COUNT}, powerup::PowerupKind}};
+
+        // One level with every chunk, and one with only tiles
+        fn test_pack() -> LevelPack {
             let mut level = Level::new();
-            *level.name_mut() = load_string(&level_bytes[0..LEVEL_NAME_LEN]);
-            
-            for (i, tiles_byte) in level_bytes[LEVEL_NAME_LEN..].iter().enumerate() {
-                let (a, b) = (tiles_byte >> 4, tiles_byte & 0b1111);
-                
-                for (index, nibble) in [(i*2, a), (i*2+1, b)] {
-                    level.tiles_mut().get_mut(index).map(|t| *t = Tile::try_from(nibble).unwrap_or(Tile::Air));
+            *level.name_mut() = String::from("EVERYTHING");
+            level.set_size(12, 18);
+            for i in 0..LEVEL_WIDTH * LEVEL_HEIGHT {
+                if i % LEVEL_WIDTH < 12 && i / LEVEL_WIDTH < 18 {
+                    level.tiles_mut()[i] = Tile::try_from((i % TILE_COUNT) as u8).unwrap();
                 }
             }
+            level.hit_points_mut()[3] = 4;
+            level.hit_points_mut()[20] = MAX_HIT_POINTS;
+            level.powerups_mut()[5] = PlacedPowerup::Random;
+            level.powerups_mut()[6] = PlacedPowerup::Kind(PowerupKind::BallsFire);
+            *level.meta_mut() = LevelMeta {
+                par_time: Some(60.0),
+                par_score: Some(1000),
+                ball_speed: Some(1.5),
+                background: Some([10, 20, 30]),
+                hint: String::from("WATCH OUT!"),
+            };
+            level.motions_mut()[1] = RowMotion { axis: MotionAxis::Horizontal, distance: 20, period: 3.0, phase: 0.25 };
+            level.motions_mut()[4] = RowMotion { axis: MotionAxis::Vertical, distance: 5, period: 1.0, phase: 0.0 };
+
+            let mut plain = Level::new();
+            *plain.name_mut() = String::from("PLAIN");
+            plain.tiles_mut()[0] = Tile::Red;
 
-            levels.push(level);
+            LevelPack { name: String::from("TEST PACK"), author: String::from("SOMEONE"), levels: vec![level, plain] }
         }
-        
-        // If the pack has no levels, or too many levels, it's not valid!!
-        if levels.is_empty() || levels.len() > MAX_LEVELS {
-            return None;
+
+        // Where each chunk's tag is and how long the chunk is
+        fn chunks(data: &[u8]) -> Vec<(u8, usize, usize)> {
+            let mut chunks = Vec::new();
+            let mut offset = PACK_MAGIC.len() + 1 + LEVEL_NAME_LEN * 2;
+            while offset < data.len() {
+                offset += LEVEL_NAME_LEN;
+                while data[offset] != CHUNK_END {
+                    let len = u32::from_le_bytes(data[offset + 1..offset + 5].try_into().unwrap()) as usize;
+                    chunks.push((data[offset], offset, len));
+                    offset += 5 + len;
+                }
+                offset += 1;
+            }
+            chunks
         }
 
-        Some(LevelPack { name, author, levels })
+        #[test]
+        fn round_trip() {
+            let pack = test_pack();
+            let data = EditorLevelPack::from(pack.clone()).encode_to_file();
+            let mut tags: Vec<u8> = chunks(&data).iter().map(|c| c.0).collect();
+            tags.sort();
+            tags.dedup();
+            assert_eq!(tags, [CHUNK_TILES, CHUNK_HIT_POINTS, CHUNK_POWERUPS, CHUNK_SIZE, CHUNK_META, CHUNK_MOTION]);
+
+            let loaded = LevelPack::load_from_file(data).unwrap();
+            assert_eq!((loaded.name(), loaded.author()), (pack.name(), pack.author()));
+            assert!(loaded.levels() == pack.levels());
+        }
+
+        #[test]
+        fn v1_resaved() {
+            let pack = LevelPack::load_from_file(include_bytes!("../../SPACE.brk").to_vec()).unwrap();
+            let loaded = LevelPack::load_from_file(EditorLevelPack::from(pack.clone()).encode_to_file()).unwrap();
+            assert!(loaded.levels() == pack.levels());
+        }
+
+        #[test]
+        fn unknown_chunks_skipped() {
+            let pack = test_pack();
+            let mut data = EditorLevelPack::from(pack.clone()).encode_to_file();
+            for (_, offset, _) in chunks(&data).into_iter().rev() {
+                data.splice(offset..offset, [200, 3, 0, 0, 0, 1, 2, 3, 201, 0, 0, 0, 0]);
+            }
+            let loaded = LevelPack::load_from_file(data).unwrap();
+            assert!(loaded.levels() == pack.levels());
+        }
+
+        #[test]
+        fn truncated_chunks() {
+            let data = EditorLevelPack::from(test_pack()).encode_to_file();
+            for (tag, offset, len) in chunks(&data) {
+                for (end, expected) in [(offset, offset), (offset + 3, offset + 1), (offset + 5 + len / 2, offset + 5), (offset + 4 + len, offset + 5)] {
+                    let result = LevelPack::load_from_file(data[..end].to_vec());
+                    assert_eq!(result.err(), Some(PackLoadError::UnexpectedEnd { offset: expected }), "chunk {tag} cut off at {end}");
+                }
+            }
+        }
     }
 }
 
@@ -76,7 +399,7 @@ pub use wasm_specific::*;
 mod wasm_specific {
     use std::sync::Mutex;
     use sapp_jsutils::JsObject;
-    use crate::LevelPack;
+    use super::{LevelPack, PackLoadError};
 
     static TRY_FLAG: Mutex<bool> = Mutex::new(false);
     
@@ -98,7 +421,8 @@ mod wasm_specific {
         unsafe { js_recv_level_bytes(JsObject::buffer(&bytes)); }
     }
 
-    pub fn try_load_level() -> Option<LevelPack> {
+    // None if there's nothing new to load
+    pub fn try_load_level() -> Option<Result<LevelPack, PackLoadError>> {
         // If the try flag couldn't be acquired (for some reason?) or if it's false, return None
         // Otherwise set it to false and carry on the function
         // Also trans rights lololol
@@ -115,6 +439,6 @@ mod wasm_specific {
         let mut buf = vec![];
         data.to_byte_buffer(&mut buf);
     
-        LevelPack::load_from_file(buf)
+        Some(LevelPack::load_from_file(buf))
     }
 }
@@ -1,25 +1,53 @@
+#[cfg(feature = "graphics")]
 use level_pack::LevelPack;
+#[cfg(feature = "graphics")]
 use macroquad::{color::{Color, WHITE}, input::{is_key_pressed, KeyCode}, math::{vec2, Vec2}, shapes::{draw_rectangle, draw_rectangle_lines}, texture::Texture2D};
+#[cfg(feature = "graphics")]
+use input::KeyboardInput;
+#[cfg(feature = "graphics")]
 use pause_menu::PauseMenu;
-use world::{level::Level, Lives, World, WorldUpdateReturn, BG_COL};
+#[cfg(feature = "graphics")]
+use replay::Replay;
+#[cfg(feature = "graphics")]
+use timestep::FixedTimestep;
+#[cfg(feature = "graphics")]
+use world::{input::InputFrame, level::Level, rng::Rng, rules::GameRules, Lives, World, WorldEvent, BG_COL, TICK_DELTA};
 
+#[cfg(feature = "graphics")]
 use crate::{gui::{BUTTON_DETAIL_GREY, BUTTON_DETAIL_HELP, GRID_COL}, text_renderer::{render_text, TextAlign}, Scene, SceneChange};
 
 pub mod world;
+pub mod bytes;
 pub mod level_pack;
+pub mod timestep;
+pub mod replay;
+#[cfg(feature = "graphics")]
+pub mod input;
+#[cfg(feature = "graphics")]
 pub mod pause_menu;
+#[cfg(feature = "graphics")]
+pub mod replay_player;
+#[cfg(feature = "graphics")]
+pub mod session;
 
+#[cfg(feature = "graphics")]
 pub const KEY_PAUSE: KeyCode = KeyCode::Escape;
 
+#[cfg(feature = "graphics")]
 #[derive(PartialEq, Eq, Debug)]
 enum TimerKind {
     NextLevel, BallStuck, GameOver,
 }
 
+#[cfg(feature = "graphics")]
 pub struct Game {
     level_pack: LevelPack,
     current_level: usize,
+    seed: u64,
     world: World,
+    timestep: FixedTimestep,
+    input: KeyboardInput,
+    replay: Replay,
 
     pause_menu: PauseMenu,
     timer: Option<(f32, TimerKind)>,
@@ -27,48 +55,49 @@ pub struct Game {
     pack_time: f32,
 }
 
+#[cfg(feature = "graphics")]
 impl Game {
-    pub fn new(level_pack: LevelPack) -> Game {
-        let world = World::new(level_pack.levels().get(0).unwrap().clone(), None, None, Lives::Default, None);
+    // Every level's world is seeded from 'seed', so the whole run can be reproduced from it
+    pub fn new(level_pack: LevelPack, seed: u64) -> Game {
+        Game::with_rules(level_pack, seed, GameRules::default())
+    }
+
+    pub fn with_rules(level_pack: LevelPack, seed: u64, rules: GameRules) -> Game {
+        let replay = Replay::new(level_pack.name().clone(), seed, rules.clone());
+        let world = World::new(level_pack.levels().first().unwrap().clone(), 0, None, None, Lives::Default, None, Rng::derive_seed(seed, 0), rules);
         Game {
             level_pack,
             current_level: 0,
+            seed,
             world,
+            timestep: FixedTimestep::new(),
+            input: KeyboardInput::new(),
+            replay,
             pause_menu: PauseMenu::new(),
             timer: None,
             pack_complete: false,
             pack_time: 0.0,
         }
     }
-}
-
-impl Scene for Game {
-    fn update(&mut self, mouse_pos: Vec2) -> Option<SceneChange> {
-        self.pause_menu.update(mouse_pos);
 
-        if is_key_pressed(KEY_PAUSE) {
-            *self.pause_menu.paused_mut() = !self.pause_menu.paused();
-        }
-        if self.pause_menu.button_exit() {
-            return Some(SceneChange::MainMenu);
-        }
-        if self.pause_menu.paused() {
-            return None;
-        }
-
-        let world_update_return = self.world.update();
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
 
-        if self.pack_complete && is_key_pressed(KeyCode::Space) {
-            return Some(SceneChange::MainMenu);
-        }
+    pub fn level_pack(&self) -> &LevelPack {
+        &self.level_pack
+    }
 
-        let delta = macroquad::time::get_frame_time();
+    // Runs a single fixed tick of the game, the world and all of the timers step together so they stay in sync
+    pub fn tick(&mut self, input: InputFrame) -> Option<SceneChange> {
+        let events = self.world.update(input);
+        let delta = TICK_DELTA;
 
         if let Some((t, _)) = &mut self.timer {
             *t = (*t - delta).max(0.0);
         }
 
-        if world_update_return == WorldUpdateReturn::BallStuck && self.timer.is_none() {
+        if events.contains(&WorldEvent::BallStuck) && self.timer.is_none() {
             self.timer = Some((3.0, TimerKind::BallStuck));
         }
         if self.timer == Some((0.0, TimerKind::BallStuck)) {
@@ -90,26 +119,77 @@ impl Scene for Game {
                     return None;
                 },
             };
-            self.world = World::new(next_level, Some(self.world.score()), Some(self.world.paddle_pos()), self.world.lives(), Some(self.world.carries()));
+            let seed = Rng::derive_seed(self.seed, self.current_level as u64);
+            self.world = World::new(next_level, self.current_level, Some(self.world.score()), Some(self.world.paddle_pos()), self.world.lives(), Some(self.world.carries()), seed, self.world.rules().clone());
             self.timer = None;
         }
 
-        if world_update_return == WorldUpdateReturn::GameOver && !matches!(self.timer, Some((_, TimerKind::GameOver)) | Some((_, TimerKind::NextLevel))) {
+        if events.contains(&WorldEvent::GameOver) && !matches!(self.timer, Some((_, TimerKind::GameOver)) | Some((_, TimerKind::NextLevel))) {
             self.timer = Some((6.0, TimerKind::GameOver));
         }
         if self.timer == Some((0.0, TimerKind::GameOver)) {
             return Some(SceneChange::MainMenu);
         }
 
-        if !(matches!(self.timer, Some((_, TimerKind::NextLevel))) || self.pack_complete || self.pause_menu.paused()) {
+        if !(matches!(self.timer, Some((_, TimerKind::NextLevel))) || self.pack_complete) {
             self.pack_time += delta;
         }
 
         None
     }
+}
+
+#[cfg(feature = "graphics")]
+impl Scene for Game {
+    fn update(&mut self, mouse_pos: Vec2) -> Option<SceneChange> {
+        self.pause_menu.update(mouse_pos);
+
+        if is_key_pressed(KEY_PAUSE) {
+            *self.pause_menu.paused_mut() = !self.pause_menu.paused();
+        }
+        if self.pause_menu.button_save() {
+            self.save_session();
+            self.pause_menu.set_saved();
+        }
+        if self.pause_menu.button_exit() {
+            self.replay.save();
+            self.save_session();
+            return Some(SceneChange::MainMenu);
+        }
+        if self.pause_menu.paused() {
+            return None;
+        }
+
+        if self.pack_complete && is_key_pressed(KeyCode::Space) {
+            self.replay.save();
+            session::clear_session();
+            return Some(SceneChange::MainMenu);
+        }
+
+        self.input.poll();
+        for _ in 0..self.timestep.advance(macroquad::time::get_frame_time()) {
+            let input = self.replay.record(self.input.take());
+            if let Some(change) = self.tick(input) {
+                // The game's over, so there's nothing left to carry on
+                self.replay.save();
+                session::clear_session();
+                return Some(change);
+            }
+        }
+
+        None
+    }
     
     fn draw(&self, texture: &Texture2D, _: Option<(&String, &String)>) {
-        self.world.draw(texture);
+        self.draw_interpolated(texture, self.timestep.alpha());
+    }
+}
+
+#[cfg(feature = "graphics")]
+impl Game {
+    // Draws the game 'alpha' of the way between the last tick and the next one
+    pub fn draw_interpolated(&self, texture: &Texture2D, alpha: f32) {
+        self.world.draw(texture, alpha);
 
         if self.pack_complete {
             let minutes = ((self.pack_time / 60.0).floor() as i32).clamp(0, 99);
@@ -128,20 +208,41 @@ impl Scene for Game {
             render_text(&format!(" TIME:  {:0>2}:{:0>2}.{:0>4}", minutes, seconds, millis), vec2(33.0, 118.0), WHITE, TextAlign::Left, texture);
             render_text(&String::from(" PRESS SPACE FOR MENU "),  vec2(33.0, 128.0), BUTTON_DETAIL_GREY, TextAlign::Left, texture);
 
-        } else
-        if matches!(self.timer, Some((t, TimerKind::NextLevel)) if t % 1.0 >= 0.5 || t >= 3.0) {
-            draw_rectangle(51.0, 83.0, 89.0, 20.0, BG_COL);
-            draw_rectangle_lines(51.0, 83.0, 89.0, 20.0, 2.0, GRID_COL);
-            render_text(&String::from("LEVEL COMPLETE"), vec2(54.0, 86.0), WHITE, TextAlign::Left, texture);
-            render_text(&String::from("  WELL DONE!  "), vec2(54.0, 94.0), WHITE, TextAlign::Left, texture);
-        } else
-        if matches!(self.timer, Some((_, TimerKind::GameOver))) {
+        } else if matches!(self.timer, Some((t, TimerKind::NextLevel)) if t % 1.0 >= 0.5 || t >= 3.0) {
+            let meta = self.world.level().meta();
+            // Any pars the level has go underneath, yellow if they were beaten
+            let mut pars = Vec::new();
+            if let Some(par) = meta.par_time {
+                pars.push((format!("TIME {}  PAR {}", format_time(self.world.time()), format_time(par)), self.world.time() <= par));
+            }
+            if let Some(par) = meta.par_score {
+                pars.push((format!("SCORE {}  PAR {}", self.world.level_score(), par), self.world.level_score() >= par));
+            }
+            match pars.is_empty() {
+                true => {
+                    draw_rectangle(51.0, 83.0, 89.0, 20.0, BG_COL);
+                    draw_rectangle_lines(51.0, 83.0, 89.0, 20.0, 2.0, GRID_COL);
+                    render_text(&String::from("LEVEL COMPLETE"), vec2(54.0, 86.0), WHITE, TextAlign::Left, texture);
+                    render_text(&String::from("  WELL DONE!  "), vec2(54.0, 94.0), WHITE, TextAlign::Left, texture);
+                }
+                false => {
+                    let h = 22.0 + pars.len() as f32 * 8.0;
+                    draw_rectangle(27.0, 83.0, 143.0, h, BG_COL);
+                    draw_rectangle_lines(27.0, 83.0, 143.0, h, 2.0, GRID_COL);
+                    render_text(&String::from("LEVEL COMPLETE"), vec2(98.5, 86.0), WHITE, TextAlign::Center, texture);
+                    render_text(&String::from("WELL DONE!"), vec2(98.5, 94.0), WHITE, TextAlign::Center, texture);
+                    for (i, (text, beaten)) in pars.iter().enumerate() {
+                        let col = if *beaten { BUTTON_DETAIL_HELP } else { BUTTON_DETAIL_GREY };
+                        render_text(text, vec2(98.5, 104.0 + i as f32 * 8.0), col, TextAlign::Center, texture);
+                    }
+                }
+            }
+        } else if matches!(self.timer, Some((_, TimerKind::GameOver))) {
             draw_rectangle(51.0, 83.0, 89.0, 20.0, BG_COL);
             draw_rectangle_lines(51.0, 83.0, 89.0, 20.0, 2.0, GRID_COL);
             render_text(&String::from("  GAME OVER!  "), vec2(54.0, 86.0), WHITE, TextAlign::Left, texture);
             render_text(&String::from(" YOU LOSE :P "), vec2(57.0, 94.0), WHITE, TextAlign::Left, texture);
-        } else
-        if matches!(self.timer, Some((_, TimerKind::BallStuck))) {
+        } else if matches!(self.timer, Some((_, TimerKind::BallStuck))) {
             draw_rectangle(27.0, 79.0, 143.0, 28.0, BG_COL);
             draw_rectangle_lines(27.0, 79.0, 143.0, 28.0, 2.0, GRID_COL);
             render_text(&String::from(" IT APPEARS YOUR BALL "),  vec2(33.0, 82.0), WHITE, TextAlign::Left, texture);
@@ -150,8 +251,19 @@ impl Scene for Game {
         }
 
         // Text
-        render_text(self.level_pack.author(), vec2(Level::view_size().x, 7.0), Color::from_rgba(255, 255, 255, 128), TextAlign::Right, &texture);
+        render_text(self.level_pack.author(), vec2(Level::view_size().x, 7.0), Color::from_rgba(255, 255, 255, 128), TextAlign::Right, texture);
 
         self.pause_menu.draw(texture);
+        if self.pause_menu.paused() {
+            render_text(&format!("SEED: {}", self.seed), vec2(0.0, 7.0), BUTTON_DETAIL_GREY, TextAlign::Left, texture);
+        }
     }
-}
\ No newline at end of file
+}
+
+// Minutes and seconds, for pars
+#[cfg(feature = "graphics")]
+fn format_time(time: f32) -> String {
+    let minutes = ((time / 60.0).floor() as i32).clamp(0, 99);
+    let seconds = ((time % 60.0).floor() as i32).clamp(0, 59);
+    format!("{:0>2}:{:0>2}", minutes, seconds)
+}
@@ -2,25 +2,34 @@ use std::collections::HashMap;
 
 use macroquad::{color::WHITE, math::{vec2, Rect, Vec2}, shapes::draw_rectangle, texture::Texture2D};
 
-use crate::{gui::{Button, ButtonDetail, Gui, BUTTON_COL_HOVER, BUTTON_COL_IDLE, DARKEN_BACKGROUND, GRID_COL}, text_renderer::{render_text, TextAlign}};
+use crate::{gui::{Button, ButtonDetail, Gui, BUTTON_COL_HOVER, BUTTON_COL_IDLE, BUTTON_DETAIL_GREY, DARKEN_BACKGROUND, GRID_COL}, text_renderer::{render_text, TextAlign}};
 
 use super::world::level::Level;
 
 pub struct PauseMenu {
     gui: Gui,
     paused: bool,
+    saved: bool,
+}
+
+impl Default for PauseMenu {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl PauseMenu {
     pub fn new() -> Self {
         let mut buttons = HashMap::new();
 
-        buttons.insert(0, Button::new(Rect::new(55.0, 105.0, 39.0, 10.0), ButtonDetail::Text(String::from("RESUME")), vec2(2.0, 2.0)));
-        buttons.insert(1, Button::new(Rect::new(98.0, 105.0, 39.0, 10.0), ButtonDetail::Text(String::from("EXIT")), vec2(8.0, 2.0)));
+        buttons.insert(0, Button::new(Rect::new(34.0, 105.0, 39.0, 10.0), ButtonDetail::Text(String::from("RESUME")), vec2(2.0, 2.0)));
+        buttons.insert(1, Button::new(Rect::new(120.0, 105.0, 39.0, 10.0), ButtonDetail::Text(String::from("EXIT")), vec2(8.0, 2.0)));
+        buttons.insert(2, Button::new(Rect::new(77.0, 105.0, 39.0, 10.0), ButtonDetail::Text(String::from("SAVE")), vec2(8.0, 2.0)));
 
         PauseMenu {
             gui: Gui::new(buttons),
             paused: false,
+            saved: false,
         }
     }
 
@@ -34,9 +43,17 @@ impl PauseMenu {
     pub fn button_exit(&self) -> bool {
         self.gui.button(1).is_some_and(|b| b.released())
     }
+    pub fn button_save(&self) -> bool {
+        self.gui.button(2).is_some_and(|b| b.released())
+    }
+    // Shows that the game was saved until it's unpaused
+    pub fn set_saved(&mut self) {
+        self.saved = true;
+    }
 
     pub fn update(&mut self, mouse_pos: Vec2) {
         if !self.paused {
+            self.saved = false;
             return;
         }
 
@@ -56,7 +73,10 @@ impl PauseMenu {
         draw_rectangle(0.0, 0.0, view_size.x, view_size.y, DARKEN_BACKGROUND);
         
         render_text(&String::from("PAUSED"), vec2(79.0, 94.0), WHITE, TextAlign::Left, texture);
-        for id in [0, 1] {
+        if self.saved {
+            render_text(&String::from("GAME SAVED!"), vec2(64.0, 119.0), BUTTON_DETAIL_GREY, TextAlign::Left, texture);
+        }
+        for id in [0, 1, 2] {
             let button = match self.gui.button(id) {
                 Some(b) => b,
                 None => continue,
@@ -0,0 +1,223 @@
+use super::{bytes::{ByteReader, ByteWriter}, world::{input::InputFrame, level::LEVEL_NAME_LEN, rules::GameRules}};
+
+// Replays are saved in the working directory as "PACK NAME.brkr", the same place the editor saves packs to
+pub const REPLAY_EXTENSION: &str = "brkr";
+
+const REPLAY_MAGIC: [u8; 4] = *b"BRKR";
+// Only replays saved with the current version can be played, older ones can't be trusted to play out the same
+const REPLAY_VERSION: u8 = 2;
+
+const FLAG_FIRE:             u8 = 0b00001;
+const FLAG_CARRY_HELD:       u8 = 0b00010;
+const FLAG_CARRY_RELEASED:   u8 = 0b00100;
+const FLAG_TEST_BALLS_LEFT:  u8 = 0b01000;
+const FLAG_TEST_BALLS_RIGHT: u8 = 0b10000;
+
+// Every tick's input, the seed and the rules of a run, which is all that's needed to play the exact same run again
+#[derive(Clone)]
+pub struct Replay {
+    pack_name: String,
+    seed: u64,
+    rules: GameRules,
+    inputs: Vec<InputFrame>,
+}
+
+impl Replay {
+    pub fn new(pack_name: String, seed: u64, rules: GameRules) -> Self {
+        Self {
+            pack_name,
+            seed,
+            rules,
+            inputs: Vec::with_capacity(1024),
+        }
+    }
+
+    pub fn pack_name(&self) -> &String {
+        &self.pack_name
+    }
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+    pub fn rules(&self) -> &GameRules {
+        &self.rules
+    }
+    pub fn inputs(&self) -> &Vec<InputFrame> {
+        &self.inputs
+    }
+
+    // Records the input for the next tick.
+    // The move axis is saved with less precision than an f32, so the input that should be given to the world is returned, that way playing it back gives exactly the same run
+    pub fn record(&mut self, input: InputFrame) -> InputFrame {
+        let input = decode_input(encode_input(&input));
+        self.inputs.push(input);
+        input
+    }
+
+    pub fn encode_to_file(&self) -> Vec<u8> {
+        let mut data = ByteWriter::new();
+
+        // The file begins with a header, the name of the pack, the seed and the rules
+        data.bytes(&REPLAY_MAGIC);
+        data.u8(REPLAY_VERSION);
+        let name = self.pack_name.as_bytes();
+        for i in 0..LEVEL_NAME_LEN {
+            data.u8(*name.get(i).unwrap_or(&0xFF));
+        }
+        data.u64(self.seed);
+        self.rules.write_snapshot(&mut data);
+
+        // Then the inputs. Input doesn't change much from tick to tick, so they're stored as runs of the same input:
+        // 2 bytes for how many ticks the run lasts, then 2 bytes for the input itself
+        let mut runs: Vec<(u16, [u8; 2])> = Vec::new();
+        for input in self.inputs.iter().map(encode_input) {
+            match runs.last_mut() {
+                Some((len, prev)) if *prev == input && *len < u16::MAX => *len += 1,
+                _ => runs.push((1, input)),
+            }
+        }
+        for (len, input) in runs {
+            data.bytes(&len.to_le_bytes());
+            data.bytes(&input);
+        }
+
+        data.into_bytes()
+    }
+
+    pub fn load_from_file(data: Vec<u8>) -> Option<Self> {
+        let mut r = ByteReader::new(&data);
+        if r.array::<4>()? != REPLAY_MAGIC {
+            return None;
+        }
+        if r.u8()? != REPLAY_VERSION {
+            return None;
+        }
+
+        let pack_name = r.bytes(LEVEL_NAME_LEN)?
+            .iter()
+            .take_while(|b| **b != 0xFF)
+            .map(|b| *b as char)
+            .collect();
+        let seed = r.u64()?;
+        let rules = GameRules::read_snapshot(&mut r)?;
+
+        let runs = r.bytes(r.remaining())?;
+        if !runs.len().is_multiple_of(4) {
+            return None;
+        }
+        let mut inputs = Vec::new();
+        for run in runs.chunks_exact(4) {
+            let len = u16::from_le_bytes([run[0], run[1]]);
+            let input = decode_input([run[2], run[3]]);
+            inputs.extend(std::iter::repeat_n(input, len as usize));
+        }
+
+        Some(Self { pack_name, seed, rules, inputs })
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save(&self) {
+        if let Err(e) = std::fs::write(replay_path(&self.pack_name), self.encode_to_file()) {
+            macroquad::logging::warn!("couldn't save replay: {}", e);
+        }
+    }
+    // There isn't anywhere to put replays in the browser (yet!!)
+    #[cfg(target_arch = "wasm32")]
+    pub fn save(&self) {}
+
+    // Loads the replay saved for the pack with this name, if there is one
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load(pack_name: &str) -> Option<Self> {
+        let replay = Replay::load_from_file(std::fs::read(replay_path(pack_name)).ok()?)?;
+        (replay.pack_name == *pack_name).then_some(replay)
+    }
+    #[cfg(target_arch = "wasm32")]
+    pub fn load(_pack_name: &str) -> Option<Self> {
+        None
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn replay_path(pack_name: &str) -> String {
+    format!("{}.{}", pack_name, REPLAY_EXTENSION)
+}
+
+fn encode_input(input: &InputFrame) -> [u8; 2] {
+    let axis = (input.move_axis.clamp(-1.0, 1.0) * 127.0).round() as i8;
+    let mut flags = 0;
+    for (set, flag) in [
+        (input.fire,             FLAG_FIRE),
+        (input.carry_held,       FLAG_CARRY_HELD),
+        (input.carry_released,   FLAG_CARRY_RELEASED),
+        (input.test_balls_left,  FLAG_TEST_BALLS_LEFT),
+        (input.test_balls_right, FLAG_TEST_BALLS_RIGHT),
+    ] {
+        if set {
+            flags |= flag;
+        }
+    }
+    [axis as u8, flags]
+}
+
+fn decode_input(bytes: [u8; 2]) -> InputFrame {
+    let (axis, flags) = (bytes[0] as i8, bytes[1]);
+    InputFrame {
+        move_axis: (axis as f32 / 127.0).clamp(-1.0, 1.0),
+        fire:             flags & FLAG_FIRE != 0,
+        carry_held:       flags & FLAG_CARRY_HELD != 0,
+        carry_released:   flags & FLAG_CARRY_RELEASED != 0,
+        test_balls_left:  flags & FLAG_TEST_BALLS_LEFT != 0,
+        test_balls_right: flags & FLAG_TEST_BALLS_RIGHT != 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_rules() -> GameRules {
+        GameRules { lives: 1, ball_speed: 90.0, powerup_gaps: vec![(1, (2, 3))], ..Default::default() }
+    }
+
+    #[test]
+    fn runs_round_trip() {
+        // Some runs longer than a byte could count, and one longer than a run can be so it gets split in two
+        let lengths = [1, 3, 255, 256, 300, 1, 70000];
+        let mut replay = Replay::new(String::from("TEST PACK"), 12, test_rules());
+        for (i, len) in lengths.iter().enumerate() {
+            let input = InputFrame {
+                move_axis: i as f32 / 10.0 - 0.3,
+                fire: i % 2 == 0,
+                carry_released: i == 5,
+                ..Default::default()
+            };
+            for _ in 0..*len {
+                replay.record(input);
+            }
+        }
+
+        let data = replay.encode_to_file();
+        let header_len = Replay::new(String::from("TEST PACK"), 12, test_rules()).encode_to_file().len();
+        assert_eq!(data.len(), header_len + (lengths.len() + 1) * 4);
+
+        let loaded = Replay::load_from_file(data).unwrap();
+        assert_eq!(loaded.inputs(), replay.inputs());
+        assert_eq!(loaded.inputs().len(), lengths.iter().sum::<usize>());
+        assert_eq!((loaded.pack_name(), loaded.seed()), (replay.pack_name(), replay.seed()));
+        assert_eq!(loaded.rules(), &test_rules());
+    }
+
+    #[test]
+    fn truncated() {
+        let mut replay = Replay::new(String::from("TEST PACK"), 12, test_rules());
+        replay.record(InputFrame { fire: true, ..Default::default() });
+        let data = replay.encode_to_file();
+        // Stopping right after the header is a replay that was saved before any ticks ran
+        let header_len = data.len() - 4;
+        for len in 0..data.len() {
+            match len == header_len {
+                true => assert!(Replay::load_from_file(data[..len].to_vec()).unwrap().inputs().is_empty()),
+                false => assert!(Replay::load_from_file(data[..len].to_vec()).is_none(), "read {len} of {} bytes", data.len()),
+            }
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use macroquad::{color::WHITE, input::{is_key_down, is_key_pressed, KeyCode}, math::{vec2, Vec2}, shapes::draw_rectangle, texture::Texture2D};
+
+use crate::{gui::{BUTTON_DETAIL_GREY, DARKEN_BACKGROUND}, text_renderer::{render_text, TextAlign}, Scene, SceneChange};
+
+use super::{level_pack::LevelPack, replay::Replay, timestep::FixedTimestep, world::level::Level, Game, KEY_PAUSE};
+
+const KEY_PLAY_PAUSE: KeyCode = KeyCode::Space;
+const KEY_FAST_FORWARD: KeyCode = KeyCode::F;
+const KEY_STEP: KeyCode = KeyCode::S;
+
+const FAST_FORWARD_SPEED: usize = 4;
+
+// Plays a recorded run back by feeding its inputs into a fresh game, one tick at a time
+pub struct ReplayPlayer {
+    game: Game,
+    replay: Replay,
+    timestep: FixedTimestep,
+
+    tick: usize,
+    paused: bool,
+    fast_forward: bool,
+}
+
+impl ReplayPlayer {
+    pub fn new(level_pack: LevelPack, replay: Replay) -> Self {
+        Self {
+            game: Game::with_rules(level_pack, replay.seed(), replay.rules().clone()),
+            replay,
+            timestep: FixedTimestep::new(),
+
+            tick: 0,
+            paused: false,
+            fast_forward: false,
+        }
+    }
+
+    pub fn finished(&self) -> bool {
+        self.tick >= self.replay.inputs().len()
+    }
+
+    fn step(&mut self) {
+        if let Some(input) = self.replay.inputs().get(self.tick) {
+            // The scene change is ignored, the game goes back to the menu when it's over but we want to stay and watch the end!
+            self.game.tick(*input);
+            self.tick += 1;
+        }
+    }
+}
+
+impl Scene for ReplayPlayer {
+    fn update(&mut self, _mouse_pos: Vec2) -> Option<SceneChange> {
+        if is_key_pressed(KEY_PAUSE) {
+            return Some(SceneChange::MainMenu);
+        }
+        if is_key_pressed(KEY_PLAY_PAUSE) {
+            self.paused = !self.paused;
+        }
+        self.fast_forward = is_key_down(KEY_FAST_FORWARD);
+
+        let ticks = self.timestep.advance(macroquad::time::get_frame_time());
+        let ticks = match (self.paused, self.fast_forward) {
+            (true, _) => if is_key_pressed(KEY_STEP) { 1 } else { 0 },
+            (_, true) => ticks * FAST_FORWARD_SPEED,
+            _ => ticks,
+        };
+        for _ in 0..ticks {
+            self.step();
+        }
+
+        None
+    }
+
+    fn draw(&self, texture: &Texture2D, _: Option<(&String, &String)>) {
+        let alpha = if self.paused || self.finished() { 1.0 } else { self.timestep.alpha() };
+        self.game.draw_interpolated(texture, alpha);
+
+        let status = match (self.finished(), self.paused, self.fast_forward) {
+            (true, _, _) => String::from("REPLAY - END"),
+            (_, true, _) => String::from("REPLAY - PAUSED"),
+            (_, _, true) => format!("REPLAY - X{}", FAST_FORWARD_SPEED),
+            _ => String::from("REPLAY"),
+        };
+        render_text(&status, vec2(0.0, 7.0), WHITE, TextAlign::Left, texture);
+
+        if self.paused || self.finished() {
+            let view_size = Level::view_size();
+            draw_rectangle(0.0, view_size.y - 22.0, view_size.x, 22.0, DARKEN_BACKGROUND);
+            render_text("SPACE: PLAY/PAUSE   F: FAST", vec2(2.0, view_size.y - 21.0), BUTTON_DETAIL_GREY, TextAlign::Left, texture);
+            render_text("S: STEP (PAUSED)  ESC: EXIT", vec2(2.0, view_size.y - 14.0), BUTTON_DETAIL_GREY, TextAlign::Left, texture);
+        }
+    }
+}
@@ -0,0 +1,214 @@
+use super::{bytes::{ByteReader, ByteWriter}, input::KeyboardInput, level_pack::LevelPack, pause_menu::PauseMenu, replay::Replay, timestep::FixedTimestep, world::{level::LEVEL_NAME_LEN, World}, Game, TimerKind};
+
+// A game that was left halfway through a pack, so it can be carried on from the main menu.
+// There's only ever one, starting a new game and leaving it overwrites the old one
+const SESSION_MAGIC: [u8; 4] = *b"BRKS";
+const SESSION_VERSION: u8 = 15;
+
+#[cfg(not(target_arch = "wasm32"))]
+const SESSION_PATH: &str = "session.brks";
+
+// The name of the pack the saved session was playing, if there is one
+pub fn saved_session_pack() -> Option<String> {
+    let data = load_session_bytes()?;
+    let mut r = ByteReader::new(&data);
+    read_header(&mut r)
+}
+
+fn read_header(r: &mut ByteReader) -> Option<String> {
+    if r.array::<4>()? != SESSION_MAGIC || r.u8()? != SESSION_VERSION {
+        return None;
+    }
+    Some(r.bytes(LEVEL_NAME_LEN)?
+        .iter()
+        .take_while(|b| **b != 0xFF)
+        .map(|b| *b as char)
+        .collect())
+}
+
+fn write_header(w: &mut ByteWriter, pack_name: &str) {
+    w.bytes(&SESSION_MAGIC);
+    w.u8(SESSION_VERSION);
+    let name = pack_name.as_bytes();
+    for i in 0..LEVEL_NAME_LEN {
+        w.u8(*name.get(i).unwrap_or(&0xFF));
+    }
+}
+
+impl Game {
+    pub fn encode_session(&self) -> Vec<u8> {
+        let mut w = ByteWriter::new();
+        write_header(&mut w, self.level_pack.name());
+
+        w.u64(self.seed);
+        w.usize(self.current_level);
+        w.f32(self.pack_time);
+        w.option(self.timer.as_ref(), |w, (t, kind)| {
+            w.f32(*t);
+            w.u8(match kind {
+                TimerKind::NextLevel => 0,
+                TimerKind::BallStuck => 1,
+                TimerKind::GameOver  => 2,
+            });
+        });
+        self.world.write_snapshot(&mut w);
+
+        // The replay so far is kept too, so it carries on recording from where it was
+        let replay = self.replay.encode_to_file();
+        w.usize(replay.len());
+        w.bytes(&replay);
+
+        w.into_bytes()
+    }
+
+    // Carries on the session in 'data', as long as it was playing 'level_pack'
+    pub fn decode_session(level_pack: LevelPack, data: &[u8]) -> Option<Game> {
+        let mut r = ByteReader::new(data);
+        if read_header(&mut r)? != *level_pack.name() {
+            return None;
+        }
+
+        let seed = r.u64()?;
+        let current_level = r.usize()?;
+        let pack_time = r.f32()?;
+        let timer = r.option(|r| Some((r.f32()?, match r.u8()? {
+            0 => TimerKind::NextLevel,
+            1 => TimerKind::BallStuck,
+            2 => TimerKind::GameOver,
+            _ => return None,
+        })))?;
+        let world = World::read_snapshot(&mut r)?;
+        let replay_len = r.usize()?;
+        let replay = Replay::load_from_file(r.bytes(replay_len)?.to_vec())?;
+
+        if current_level >= level_pack.levels().len() || !r.finished() {
+            return None;
+        }
+
+        Some(Game {
+            level_pack,
+            current_level,
+            seed,
+            world,
+            timestep: FixedTimestep::new(),
+            input: KeyboardInput::new(),
+            replay,
+            pause_menu: PauseMenu::new(),
+            timer,
+            pack_complete: false,
+            pack_time,
+        })
+    }
+
+    // A game that's over or finished can't be carried on, so that just gets rid of the old session
+    pub fn save_session(&self) {
+        match self.pack_complete || matches!(self.timer, Some((_, TimerKind::GameOver))) {
+            true => clear_session(),
+            false => save_session_bytes(self.encode_session()),
+        }
+    }
+
+    // Loads the saved session if it was playing 'level_pack'
+    pub fn resume_session(level_pack: LevelPack) -> Option<Game> {
+        Game::decode_session(level_pack, &load_session_bytes()?)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save_session_bytes(bytes: Vec<u8>) {
+    if let Err(e) = std::fs::write(SESSION_PATH, bytes) {
+        macroquad::logging::warn!("couldn't save session: {}", e);
+    }
+}
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load_session_bytes() -> Option<Vec<u8>> {
+    std::fs::read(SESSION_PATH).ok()
+}
+#[cfg(not(target_arch = "wasm32"))]
+pub fn clear_session() {
+    let _ = std::fs::remove_file(SESSION_PATH);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::world::input::InputFrame;
+
+    fn test_session() -> (LevelPack, Vec<u8>) {
+        let level_pack = LevelPack::load_from_file(include_bytes!("../../ALBUMS I LIKE.brk").to_vec()).unwrap();
+        let mut game = Game::new(level_pack.clone(), 3);
+        for tick in 0_usize..2000 {
+            let input = game.replay.record(InputFrame {
+                move_axis: (tick as f32 / 50.0).sin(),
+                fire: true,
+                carry_released: tick.is_multiple_of(120),
+                ..Default::default()
+            });
+            game.world.update(input);
+        }
+        let data = game.encode_session();
+        (level_pack, data)
+    }
+
+    #[test]
+    fn session_round_trip() {
+        let (level_pack, data) = test_session();
+        let game = Game::decode_session(level_pack, &data).unwrap();
+        assert_eq!(game.encode_session(), data);
+        assert_eq!(game.replay.inputs().len(), 2000);
+    }
+
+    #[test]
+    fn session_truncated() {
+        let (level_pack, data) = test_session();
+        for len in 0..data.len() {
+            assert!(Game::decode_session(level_pack.clone(), &data[..len]).is_none(), "read {len} of {} bytes", data.len());
+        }
+    }
+
+    #[test]
+    fn session_wrong_version() {
+        let (level_pack, mut data) = test_session();
+        assert_eq!(read_header(&mut ByteReader::new(&data)).as_ref(), Some(level_pack.name()));
+
+        data[4] = SESSION_VERSION - 1;
+        assert!(read_header(&mut ByteReader::new(&data)).is_none());
+        assert!(Game::decode_session(level_pack.clone(), &data).is_none());
+
+        data[4] = SESSION_VERSION;
+        data[0] = b'X';
+        assert!(read_header(&mut ByteReader::new(&data)).is_none());
+        assert!(Game::decode_session(level_pack, &data).is_none());
+    }
+}
+
+// In the browser the session lives in local storage, see script.js
+#[cfg(target_arch = "wasm32")]
+pub use wasm_specific::*;
+#[cfg(target_arch = "wasm32")]
+mod wasm_specific {
+    use sapp_jsutils::JsObject;
+
+    #[no_mangle]
+    extern "C" {
+        fn js_save_session(buffer: JsObject);
+        fn js_load_session() -> JsObject;
+        fn js_clear_session();
+    }
+
+    pub fn save_session_bytes(bytes: Vec<u8>) {
+        unsafe { js_save_session(JsObject::buffer(&bytes)); }
+    }
+    pub fn load_session_bytes() -> Option<Vec<u8>> {
+        let data = unsafe { js_load_session() };
+        if data.is_nil() {
+            return None;
+        }
+        let mut buf = vec![];
+        data.to_byte_buffer(&mut buf);
+        Some(buf)
+    }
+    pub fn clear_session() {
+        unsafe { js_clear_session(); }
+    }
+}
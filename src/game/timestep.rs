@@ -0,0 +1,34 @@
+use super::world::TICK_DELTA;
+
+// Never try to catch up on more than this much time in one frame, otherwise a long hitch (dragging the window, etc.) would make the game run a burst of ticks
+const MAX_FRAME_TIME: f32 = 0.25;
+
+// Turns the variable frame time into a whole number of fixed ticks, keeping any leftover time for the next frame
+pub struct FixedTimestep {
+    accumulator: f32,
+}
+
+impl Default for FixedTimestep {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FixedTimestep {
+    pub fn new() -> Self {
+        Self { accumulator: 0.0 }
+    }
+
+    // Adds the frame time and returns how many ticks should be run this frame
+    pub fn advance(&mut self, frame_time: f32) -> usize {
+        self.accumulator = (self.accumulator + frame_time).min(MAX_FRAME_TIME);
+        let ticks = (self.accumulator / TICK_DELTA).floor() as usize;
+        self.accumulator -= ticks as f32 * TICK_DELTA;
+        ticks
+    }
+
+    // How far between the last tick and the next one we are, from 0.0 to 1.0, used to interpolate drawing
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator / TICK_DELTA).clamp(0.0, 1.0)
+    }
+}
@@ -1,17 +1,41 @@
-use macroquad::{color::WHITE, math::{vec2, BVec2, Rect, Vec2}, rand::gen_range, texture::{draw_texture_ex, DrawTextureParams, Texture2D}};
+use macroquad::math::{vec2, Rect, Vec2};
+#[cfg(feature = "graphics")]
+use macroquad::{color::WHITE, texture::{draw_texture_ex, DrawTextureParams, Texture2D}};
 
-use super::{level::{Level, Tile, LEVEL_HEIGHT, LEVEL_HEIGHT_PADDING_TOP, LEVEL_WIDTH, TILE_GAP, TILE_HEIGHT, TILE_WIDTH}, paddle::Paddle};
+use crate::game::bytes::{ByteReader, ByteWriter};
+
+use super::{level::{Level, LEVEL_HEIGHT, LEVEL_WIDTH}, paddle::Paddle, rng::Rng, rules::GameRules};
 
 pub const BALL_SIZE: f32 = 4.0;
-pub const BALL_SPEED: f32 = 70.0;
+
+// The most things a ball can bounce off in a single tick
+const MAX_BOUNCES: usize = 4;
+// Contacts this close together in time happen at once
+const CONTACT_EPSILON: f32 = 0.0001;
 
 pub const BALL_TEXTURE: Rect = Rect { x: 113.0, y: 8.0, w: 4.0, h: 4.0 };
+pub const FIREBALL_TEXTURE: Rect = Rect { x: 113.0, y: 31.0, w: 4.0, h: 4.0 };
 
 pub struct Ball {
     pos: Vec2,
+    prev_pos: Vec2,
     vel: Vec2,
 }
 
+enum ContactWith {
+    Wall,
+    Paddle,
+    Tiles(Vec<usize>),
+}
+
+struct Contact {
+    // How far along the motion the contact happens, 0.0 to 1.0
+    time: f32,
+    // Which way the surface that was hit faces. Both axes are set when hitting a corner
+    normal: Vec2,
+    with: ContactWith,
+}
+
 #[derive(PartialEq, Eq)]
 pub enum BallHitState {
     None,
@@ -24,6 +48,7 @@ impl Ball {
     pub fn new(pos: Vec2, angle: f32, speed: f32) -> Self {
         Self {
             pos,
+            prev_pos: pos,
             vel: Vec2::from_angle(angle) * speed,
         }
     }
@@ -34,122 +59,255 @@ impl Ball {
     pub fn vel(&self) -> Vec2 {
         self.vel
     }
+    // Teleports the ball, so it won't be interpolated from wherever it was before
     pub fn set_pos(&mut self, pos: Vec2) {
         self.pos = pos;
+        self.prev_pos = pos;
     }
     pub fn set_vel(&mut self, vel: Vec2) {
         self.vel = vel;
     }
 
-    pub fn update(&mut self, delta: f32, paddle: &Paddle, level: &Level, safe: bool) -> BallHitState {
-        let mut prev_pos = self.pos;
-        let rect = Rect::new(0.0, 0.0, BALL_SIZE, BALL_SIZE);
-        let mut bounce = BVec2::new(false, false);
-        
-        self.pos += self.vel * delta * BALL_SPEED;
-
-        // Ideal approch - check the 3x3 area of tiles around the ball rather than all of them
-        let mut tiles_to_check = Vec::with_capacity(9);
-        let ball_tile_pos = (self.pos - vec2(0.0, LEVEL_HEIGHT_PADDING_TOP as f32 * TILE_HEIGHT)) / (vec2(TILE_WIDTH, TILE_HEIGHT) + TILE_GAP);
-        
-        for x in -1..=1 {
-            for y in -1..=1 {
-                let tile_pos = ball_tile_pos + vec2(x as f32, y as f32);
-                let tile_index = match tile_pos.x < 0.0 || tile_pos.x >= LEVEL_WIDTH as f32 || tile_pos.y < 0.0 || tile_pos.x >= LEVEL_HEIGHT as f32 {
-                    false => tile_pos.y.floor() as usize * LEVEL_WIDTH + tile_pos.x.floor() as usize,
-                    true => continue,
-                };
-                tiles_to_check.push(tile_index);
-            }
-        }
+    pub fn write_snapshot(&self, w: &mut ByteWriter) {
+        w.vec2(self.pos);
+        w.vec2(self.prev_pos);
+        w.vec2(self.vel);
+    }
+    pub fn read_snapshot(r: &mut ByteReader) -> Option<Self> {
+        Some(Self { pos: r.vec2()?, prev_pos: r.vec2()?, vel: r.vec2()? })
+    }
+
+    pub fn update(&mut self, delta: f32, paddle: &Paddle, level: &Level, safe: bool, rng: &mut Rng, rules: &GameRules) -> BallHitState {
+        self.prev_pos = self.pos;
 
         let mut hit_tiles = Vec::new();
-        for i in tiles_to_check {
-            if !level.tiles().get(i).is_some_and(|t| *t != Tile::Air) {
-                continue;
-            }
+        let mut hit_paddle = false;
+        // Tiles a fireball's already gone into, which are still there until the world breaks them
+        let mut pierced = Vec::new();
 
-            let tile_rect = Level::tile_rect(i);
+        // The ball is swept along its path, stopping at the first thing it touches, bouncing off it and carrying on with the rest of the step.
+        // This way nothing can be skipped over no matter how fast the ball is going
+        let mut remaining = 1.0;
+        let speed = rules.ball_speed * level.meta().ball_speed.unwrap_or(1.0) * if paddle.balls_fast() { rules.fast_ball_speed } else { 1.0 } * paddle.time_scale(rules);
+        for _ in 0..MAX_BOUNCES {
+            let motion = self.vel * delta * speed * remaining;
+            let contact = match self.first_contact(motion, delta * remaining, paddle, level, safe, &pierced) {
+                Some(c) => c,
+                None => {
+                    self.pos += motion;
+                    break;
+                }
+            };
 
-            let mut break_tile = false;
-            if tile_rect.overlaps(&rect.offset(vec2(self.pos.x, prev_pos.y))) {
-                bounce.x = true;
-                break_tile = true;
-            }
-            if tile_rect.overlaps(&rect.offset(vec2(prev_pos.x, self.pos.y))) {
-                bounce.y = true;
-                break_tile = true;
-            }
-            if break_tile {
-                hit_tiles.push(i);
+            self.pos += motion * contact.time;
+            remaining *= 1.0 - contact.time;
+
+            match contact.with {
+                ContactWith::Paddle => {
+                    hit_paddle = true;
+                    self.bounce_off_paddle(paddle, rng, rules);
+                }
+                ContactWith::Tiles(tiles) => {
+                    // Going into a portal comes straight back out of its partner, with nothing hit
+                    if let Some(exit) = tiles.iter().find_map(|i| self.portal_exit(level, *i, contact.normal)) {
+                        self.set_pos(exit);
+                        continue;
+                    }
+                    let push = level.tile_velocity(tiles[0]) / speed;
+                    // Fireballs go straight through anything they can break, unless there's something solid there too
+                    let pierce = paddle.fireball() && tiles.iter().all(|i| rules.material(level.tiles()[*i]).ball_breakable);
+                    for i in tiles {
+                        if !hit_tiles.contains(&i) {
+                            hit_tiles.push(i);
+                        }
+                        if pierce {
+                            pierced.push(i);
+                        }
+                    }
+                    if pierce {
+                        continue;
+                    }
+                    self.bounce(contact.normal);
+                    // A moving tile catching up with the ball after it bounces would go straight through it, so it carries the ball along instead
+                    for axis in 0..2 {
+                        if contact.normal[axis] != 0.0 && (self.vel[axis] - push[axis]) * contact.normal[axis] < 0.0 {
+                            self.vel[axis] = push[axis];
+                        }
+                    }
+                }
+                ContactWith::Wall => self.bounce(contact.normal),
             }
         }
 
-        let mut hit_paddle = false;
-        if self.vel.y > 0.0 && paddle.collision_rect().overlaps(&rect.offset(vec2(prev_pos.x, self.pos.y))) {
-            self.pos = prev_pos;
-            bounce.y = true;
-            hit_paddle = true;
+        match (!hit_tiles.is_empty(), self.pos.y >= Level::view_size().y, hit_paddle) {
+            (true, _, _) => BallHitState::Tiles(hit_tiles),
+            (_, true, _) => BallHitState::Floor,
+            (_, _, true) => BallHitState::Paddle,
+            _ => BallHitState::None,
+        }
+    }
 
-            let center_dist = paddle.center_dist(self.pos.x + BALL_SIZE / 2.0);
-            let magnitude = self.vel.length();
-            let angle = self.vel.angle_between(vec2(-1.0, 0.0));
-            
-            let new_angle = angle.to_degrees() - 30.0 * center_dist * self.vel.x.signum();
-            let new_angle = new_angle.clamp(90.0 - 60.0, 90.0 + 60.0);
+    // Where the ball comes out if it touches the portal 'index' on the side facing 'normal'.
+    // It leaves from the opposite side of the partner, the same distance along it. Corners and blocked exits just bounce
+    fn portal_exit(&self, level: &Level, index: usize, normal: Vec2) -> Option<Vec2> {
+        let (from, to) = (level.tile_rect(index), level.tile_rect(level.portal_partner(index)?));
+        let mut pos = to.point() + (self.pos - from.point());
+        match (normal.x as i32, normal.y as i32) {
+            ( 1, 0) => pos.x = to.x - BALL_SIZE,
+            (-1, 0) => pos.x = to.right(),
+            (0,  1) => pos.y = to.y - BALL_SIZE,
+            (0, -1) => pos.y = to.bottom(),
+            _ => return None,
+        }
 
-            let new_magnitude = (magnitude * gen_range(1.0, 1.05)).clamp(1.0, 1.3);
+        // Shrunk a little so the partner it's touching doesn't count
+        let rect = Rect::new(pos.x + 0.01, pos.y + 0.01, BALL_SIZE - 0.02, BALL_SIZE - 0.02);
+        let max = Level::view_size() - BALL_SIZE;
+        match (0.0..=max.x).contains(&pos.x) && (0.0..=max.y).contains(&pos.y) && level.tiles_in_rect(rect).next().is_none() {
+            true => Some(pos),
+            false => None,
+        }
+    }
 
-            self.vel = Vec2::from_angle(new_angle.to_radians()) * new_magnitude * vec2(-1.0, 1.0);
-            // self.vel.y *= 1.0 + center_dist * 0.2; 
+    // Reflects the velocity along any axis the normal points against it in
+    fn bounce(&mut self, normal: Vec2) {
+        if normal.x != 0.0 && normal.x.signum() != self.vel.x.signum() {
+            self.vel.x *= -1.0;
+        }
+        if normal.y != 0.0 && normal.y.signum() != self.vel.y.signum() {
+            self.vel.y *= -1.0;
+        }
+    }
 
-            // let angle = match center_dist.abs() {
-            //     0.6.. => 70,
-            //     _ => 3,
-            // };
+    // Where on the paddle the ball lands changes the angle it goes off at
+    fn bounce_off_paddle(&mut self, paddle: &Paddle, rng: &mut Rng, rules: &GameRules) {
+        let center_dist = paddle.center_dist(self.pos.x + BALL_SIZE / 2.0);
+        let magnitude = self.vel.length();
+        let angle = self.vel.angle_between(vec2(-1.0, 0.0));
 
-            // let angle: f32 = 70.0_f32.to_radians() * center_dist * self.vel.x.signum();
-            // self.vel = self.vel.rotate(Vec2::from_angle(angle));
+        let new_angle = angle.to_degrees() - 30.0 * center_dist * self.vel.x.signum();
+        let new_angle = new_angle.clamp(90.0 - 60.0, 90.0 + 60.0);
 
-            // draw_line(self.pos.x, self.pos.y, self.pos.x + Vec2::from_angle(angle).x * 20.0, self.pos.y + Vec2::from_angle(angle).y * 20.0, 2.0, PINK);
+        let new_magnitude = (magnitude * rng.gen_range(1.0, 1.05)).clamp(rules.ball_min_speed, rules.ball_max_speed);
 
-            // let angle = 45.0_f32.to_radians() * paddle.center_dist(self.pos.x) * self.vel.x.signum();
-            // self.vel = self.vel.rotate(Vec2::from_angle(angle));
-        }
+        // Flipped on both axes, so it goes back up
+        self.vel = Vec2::from_angle(new_angle.to_radians()) * new_magnitude * vec2(-1.0, -1.0);
+    }
+
+    // Finds the first thing the ball would touch if it moved by 'motion', apart from the tiles in 'ignore'
+    // 'step_time' is how long the motion takes, so moving tiles can be moved along with it
+    fn first_contact(&self, motion: Vec2, step_time: f32, paddle: &Paddle, level: &Level, safe: bool, ignore: &[usize]) -> Option<Contact> {
+        let mut first: Option<Contact> = None;
+        let mut consider = |time: f32, normal: Vec2, with: ContactWith| {
+            if time > 1.0 {
+                return;
+            }
+            match &mut first {
+                // Touching several tiles at once (e.g. hitting the seam between two) counts as hitting all of them
+                Some(c) if (c.time - time).abs() <= CONTACT_EPSILON => {
+                    c.normal = (c.normal + normal).clamp(Vec2::NEG_ONE, Vec2::ONE);
+                    match (&mut c.with, with) {
+                        (ContactWith::Tiles(tiles), ContactWith::Tiles(new)) => tiles.extend(new),
+                        (ContactWith::Wall, with @ (ContactWith::Tiles(_) | ContactWith::Paddle)) => c.with = with,
+                        _ => (),
+                    }
+                }
+                Some(c) if c.time < time => (),
+                _ => first = Some(Contact { time, normal, with }),
+            }
+        };
 
-        if self.pos.x <= 0.0 || self.pos.x >= Level::view_size().x - BALL_SIZE {
-            bounce.x = true;
+        // Walls, and the floor if the ball is safe
+        let view_size = Level::view_size();
+        let max = view_size - BALL_SIZE;
+        if motion.x < 0.0 {
+            consider(wall_time(self.pos.x, motion.x, 0.0), vec2(1.0, 0.0), ContactWith::Wall);
         }
-        if self.pos.y <= 0.0 || (safe && self.pos.y >= Level::view_size().y - BALL_SIZE) {
-            prev_pos.y = prev_pos.y.min(Level::view_size().y - BALL_SIZE);
-            bounce.y = true;
+        if motion.x > 0.0 {
+            consider(wall_time(self.pos.x, motion.x, max.x), vec2(-1.0, 0.0), ContactWith::Wall);
         }
-
-        if bounce.any() {
-            // self.vel = self.vel.rotate(Vec2::from_angle(gen_range(-0.05, 0.05)));
+        if motion.y < 0.0 {
+            consider(wall_time(self.pos.y, motion.y, 0.0), vec2(0.0, 1.0), ContactWith::Wall);
         }
-
-        if bounce.x {
-            self.pos.x = prev_pos.x;
-            self.vel.x *= -1.0;
+        if motion.y > 0.0 && safe {
+            consider(wall_time(self.pos.y, motion.y, max.y), vec2(0.0, -1.0), ContactWith::Wall);
         }
-        if bounce.y {
-            self.pos.y = prev_pos.y;
-            self.vel.y *= -1.0;
+
+        // The paddle can only be hit from above
+        if motion.y > 0.0 {
+            if let Some((time, normal)) = sweep(self.pos, motion, paddle.collision_rect()) {
+                if normal.y < 0.0 {
+                    consider(time, vec2(0.0, -1.0), ContactWith::Paddle);
+                }
+            }
         }
 
-        match (!hit_tiles.is_empty(), self.pos.y >= Level::view_size().y, hit_paddle) {
-            (true, _, _) => BallHitState::Tiles(hit_tiles),
-            (_, true, _) => BallHitState::Floor,
-            (_, _, true) => BallHitState::Paddle,
-            _ => BallHitState::None,
+        // Only the tiles the ball passes over on the way need checking, plus however far the fastest row could move into it.
+        // Moving tiles are swept from where they were at the start of the step, with the ball's motion taken relative to them
+        let ball_rect = Rect::new(self.pos.x, self.pos.y, BALL_SIZE, BALL_SIZE);
+        let reach = (0..LEVEL_HEIGHT).map(|row| level.tile_velocity(row * LEVEL_WIDTH).abs().max_element()).fold(0.0, f32::max) * step_time;
+        let swept_rect = ball_rect.combine_with(ball_rect.offset(motion));
+        let swept_rect = Rect::new(swept_rect.x - reach, swept_rect.y - reach, swept_rect.w + reach * 2.0, swept_rect.h + reach * 2.0);
+        for i in level.tiles_in_rect(swept_rect).filter(|i| !ignore.contains(i)) {
+            let shift = level.tile_velocity(i) * step_time;
+            if let Some((time, normal)) = sweep(self.pos, motion - shift, level.tile_rect(i).offset(-shift)) {
+                consider(time, normal, ContactWith::Tiles(vec![i]));
+            }
         }
+
+        first
+    }
+
+    #[cfg(feature = "graphics")]
+    pub fn draw(&self, texture: &Texture2D, alpha: f32, fire: bool) {
+        self.draw_at(texture, self.prev_pos.lerp(self.pos, alpha), fire);
     }
 
-    pub fn draw(&self, texture: &Texture2D) {
-        draw_texture_ex(texture, self.pos.x, self.pos.y, WHITE, DrawTextureParams {
-            source: Some(BALL_TEXTURE),
+    #[cfg(feature = "graphics")]
+    pub fn draw_at(&self, texture: &Texture2D, pos: Vec2, fire: bool) {
+        draw_texture_ex(texture, pos.x, pos.y, WHITE, DrawTextureParams {
+            source: Some(if fire { FIREBALL_TEXTURE } else { BALL_TEXTURE }),
             ..Default::default()
         });
     }
-}
\ No newline at end of file
+}
+
+// When something moving along one axis from 'pos' by 'motion' reaches 'bound'
+fn wall_time(pos: f32, motion: f32, bound: f32) -> f32 {
+    ((bound - pos) / motion).max(0.0)
+}
+
+// Sweeps a ball at 'pos' along 'motion' against 'rect', returning when it first touches and the normal of the side it touches
+fn sweep(pos: Vec2, motion: Vec2, rect: Rect) -> Option<(f32, Vec2)> {
+    // Growing the rect by the size of the ball means only the ball's corner needs tracing, as a ray
+    let min = vec2(rect.x - BALL_SIZE, rect.y - BALL_SIZE);
+    let max = vec2(rect.x + rect.w, rect.y + rect.h);
+
+    let mut entry = Vec2::NEG_INFINITY;
+    let mut exit = Vec2::INFINITY;
+    for axis in 0..2 {
+        if motion[axis] == 0.0 {
+            // Not moving along this axis, so it has to be between the sides already
+            if pos[axis] <= min[axis] || pos[axis] >= max[axis] {
+                return None;
+            }
+            continue;
+        }
+        let (a, b) = ((min[axis] - pos[axis]) / motion[axis], (max[axis] - pos[axis]) / motion[axis]);
+        entry[axis] = a.min(b);
+        exit[axis] = a.max(b);
+    }
+
+    let time = entry.max_element();
+    // Contacts just behind the start are allowed, so a ball resting against something can't slip into it
+    if time > exit.min_element() || !(-CONTACT_EPSILON..=1.0).contains(&time) || exit.min_element() <= 0.0 {
+        return None;
+    }
+
+    let normal = vec2(
+        if entry.x >= entry.y { -motion.x.signum() } else { 0.0 },
+        if entry.y >= entry.x { -motion.y.signum() } else { 0.0 },
+    );
+    Some((time.max(0.0), normal))
+}
+
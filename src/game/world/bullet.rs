@@ -1,6 +1,10 @@
-use macroquad::{color::WHITE, math::{vec2, Rect, Vec2}, texture::{draw_texture_ex, DrawTextureParams, Texture2D}};
+use macroquad::math::{vec2, Rect, Vec2};
+#[cfg(feature = "graphics")]
+use macroquad::{color::WHITE, texture::{draw_texture_ex, DrawTextureParams, Texture2D}};
 
-use super::level::Level;
+use crate::game::bytes::{ByteReader, ByteWriter};
+
+use super::{level::Level, rules::GameRules};
 
 const BULLET_SPEED: f32 = 200.0;
 const BULLET_HEIGHT: f32 = 6.0;
@@ -9,6 +13,7 @@ pub const BULLET_TEXTURE: Rect = Rect { x: 130.0, y: 8.0, w: 1.0, h: 1.0 };
 
 pub struct Bullet {
     pos: Vec2,
+    prev_pos: Vec2,
 }
 
 #[derive(PartialEq, Eq)]
@@ -20,20 +25,30 @@ pub enum BulletHitState {
 
 impl Bullet {
     pub fn new(pos: Vec2) -> Self {
-        Self { pos }
+        Self { pos, prev_pos: pos }
+    }
+    pub fn pos(&self) -> Vec2 {
+        self.pos
+    }
+
+    pub fn write_snapshot(&self, w: &mut ByteWriter) {
+        w.vec2(self.pos);
+        w.vec2(self.prev_pos);
     }
-    pub fn update(&mut self, delta: f32, level: &Level) -> BulletHitState {
+    pub fn read_snapshot(r: &mut ByteReader) -> Option<Self> {
+        Some(Self { pos: r.vec2()?, prev_pos: r.vec2()? })
+    }
+    // Bullets go straight through anything they can't break, so the first tile along the way they can break is the one that's hit
+    pub fn update(&mut self, delta: f32, level: &Level, rules: &GameRules) -> BulletHitState {
+        self.prev_pos = self.pos;
         self.pos.y -= delta * BULLET_SPEED;
 
-        let rect = Rect::new(self.pos.x, self.pos.y + BULLET_HEIGHT, 2.0, 1.0);
-        let mut hit_tile = None;
-        for (i, t) in level.tiles().iter().enumerate() {
-            if Level::tile_rect(i).overlaps(&rect) && t.breakable() {
-                hit_tile = Some(i);
-                break;
-            }
-        }
-        
+        let (from, to) = (self.prev_pos + vec2(0.5, BULLET_HEIGHT + 1.0), self.pos + vec2(0.5, BULLET_HEIGHT));
+        let hit_tile = level
+            .tiles_on_segment(from, to)
+            .into_iter()
+            .find(|i| rules.material(level.tiles()[*i]).bullet_breakable);
+
         match (hit_tile, self.pos.y < -10.0) {
             (Some(i), _) => BulletHitState::Tile(i),
             (_, true)    => BulletHitState::Roof,
@@ -41,8 +56,10 @@ impl Bullet {
         }
     }
 
-    pub fn draw(&self, texture: &Texture2D) {
-        draw_texture_ex(texture, self.pos.x, self.pos.y - BULLET_HEIGHT, WHITE, DrawTextureParams {
+    #[cfg(feature = "graphics")]
+    pub fn draw(&self, texture: &Texture2D, alpha: f32) {
+        let pos = self.prev_pos.lerp(self.pos, alpha);
+        draw_texture_ex(texture, pos.x, pos.y - BULLET_HEIGHT, WHITE, DrawTextureParams {
             source: Some(BULLET_TEXTURE),
             dest_size: Some(vec2(1.0, BULLET_HEIGHT)),
             ..Default::default()
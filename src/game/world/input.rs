@@ -0,0 +1,14 @@
+// Everything the player can do to the world in a single tick.
+// The world never reads the keyboard itself, so it can be driven by anything that can fill one of these in (keyboard, replays, tests...)
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct InputFrame {
+    // -1.0 is fully left, 1.0 is fully right
+    pub move_axis: f32,
+    pub fire: bool,
+    pub carry_held: bool,
+    pub carry_released: bool,
+
+    // For testing, spawns a handful of balls on the left / right
+    pub test_balls_left: bool,
+    pub test_balls_right: bool,
+}
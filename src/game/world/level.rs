@@ -1,5 +1,16 @@
-use macroquad::{color::WHITE, math::{vec2, Rect, Vec2}, texture::{draw_texture_ex, DrawTextureParams, Texture2D}};
+use std::f32::consts::TAU;
 
+use macroquad::math::{vec2, Rect, Vec2};
+#[cfg(feature = "graphics")]
+use macroquad::{color::{Color, WHITE}, shapes::draw_rectangle, texture::{draw_texture_ex, DrawTextureParams, Texture2D}};
+#[cfg(feature = "graphics")]
+use crate::text_renderer::{render_text, TextAlign};
+
+use crate::{game::bytes::{ByteReader, ByteWriter}, text_renderer::char_valid};
+
+use super::{material::MaterialTable, powerup::PlacedPowerup};
+
+// The most tiles a level can have across and down. Smaller levels are centred in the same space
 pub const LEVEL_WIDTH: usize = 16;
 pub const LEVEL_HEIGHT: usize = 22;
 
@@ -11,17 +22,35 @@ pub const TILE_HEIGHT: f32 = 6.0;
 pub const TILE_GAP: f32 = 1.0;
 
 pub const LEVEL_NAME_LEN: usize = 16;
+pub const LEVEL_HINT_LEN: usize = 28;
 
+// The sprite sheet only fits this many tiles across, any after that are on another row further down
+const TILE_SHEET_COLUMNS: usize = 16;
+const TILE_SHEET_SECOND_ROW_Y: f32 = 31.0;
+
+// Always big enough for the largest level, with each row LEVEL_WIDTH long whatever size the level is.
+// Anything outside of the level's size is left empty
 pub type TileArray = [Tile; LEVEL_WIDTH*LEVEL_HEIGHT];
+// How many more hits each tile takes before it actually gets hit, 1 means the next hit does it.
+// 0 means the level hasn't set it, so the tile takes however many its material says
+pub type HitPointArray = [u8; LEVEL_WIDTH*LEVEL_HEIGHT];
+
+pub const MAX_HIT_POINTS: u8 = 9;
+pub type PowerupArray = [PlacedPowerup; LEVEL_WIDTH*LEVEL_HEIGHT];
 
 
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum Tile {
     White, Red, Orange, Yellow, Green, Cyan, Blue, Purple, Pink, Brown, Black,
     Stone, StoneCracked,
     Metal, Gold,
     Air,
+    // Hits everything around it a moment after it breaks
+    Explosive,
+    // Balls going into one come out of its partner, see 'Level::portal_partner'
+    PortalA, PortalB,
 }
+pub const TILE_COUNT: usize = 19;
 
 impl TryFrom<u8> for Tile {
     type Error = ();
@@ -43,6 +72,9 @@ impl TryFrom<u8> for Tile {
             13 => Ok(Tile::Metal),
             14 => Ok(Tile::Gold),
             15 => Ok(Tile::Air),
+            16 => Ok(Tile::Explosive),
+            17 => Ok(Tile::PortalA),
+            18 => Ok(Tile::PortalB),
             _ => Err(())
         }
     }
@@ -51,20 +83,21 @@ impl TryFrom<u8> for Tile {
 impl Tile {
     pub fn texture_rect(&self) -> Rect {
         let along = *self as usize;
+        let (along, y) = match along < TILE_SHEET_COLUMNS {
+            true  => (along, 1.0),
+            false => (along - TILE_SHEET_COLUMNS, TILE_SHEET_SECOND_ROW_Y),
+        };
         Rect {
             x: along as f32 * (TILE_WIDTH + 1.0) + 1.0,
-            y: 1.0,
+            y,
             w: TILE_WIDTH,
             h: TILE_HEIGHT,
         }
     }
 
-    pub fn breakable(&self) -> bool {
-        !matches!(*self, Self::Air | Self::Metal | Self::Gold)
-    }
-
+    // Whether something can break it is up to its material, this is just what it turns into
     pub fn hit(&mut self) -> bool {
-        if !self.breakable() {
+        if *self == Self::Air {
             return false;
         }
         *self = match *self {
@@ -75,17 +108,156 @@ impl Tile {
     }
 }
 
-#[derive(Clone)]
+// Optional extras a level can have, anything left as None just uses whatever the game normally does
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct LevelMeta {
+    // Seconds, from the first launch of the ball
+    pub par_time: Option<f32>,
+    pub par_score: Option<usize>,
+    // Multiplies how fast the balls move
+    pub ball_speed: Option<f32>,
+    pub background: Option<[u8; 3]>,
+    // Shown before the ball is first launched, empty for none
+    pub hint: String,
+}
+
+impl LevelMeta {
+    #[cfg(feature = "graphics")]
+    pub fn background_color(&self) -> Option<Color> {
+        self.background.map(|[r, g, b]| Color::from_rgba(r, g, b, 255))
+    }
+
+    pub fn write(&self, w: &mut ByteWriter) {
+        w.option(self.par_time, |w, v| w.f32(v));
+        w.option(self.par_score, |w, v| w.usize(v));
+        w.option(self.ball_speed, |w, v| w.f32(v));
+        w.option(self.background, |w, v| w.bytes(&v));
+        w.u8(self.hint.len() as u8);
+        w.bytes(self.hint.as_bytes());
+    }
+    pub fn read(r: &mut ByteReader) -> Option<Self> {
+        let par_time = r.option(|r| r.f32())?;
+        let par_score = r.option(|r| r.usize())?;
+        let ball_speed = r.option(|r| r.f32())?;
+        let background = r.option(|r| r.array())?;
+        let hint_len = r.u8()? as usize;
+        let hint = r.bytes(hint_len)?;
+        if hint_len > LEVEL_HINT_LEN || !hint.iter().all(|b| char_valid(*b as char))
+        || par_time.is_some_and(|t| !t.is_finite() || t <= 0.0)
+        || ball_speed.is_some_and(|s| !s.is_finite() || s <= 0.0) {
+            return None;
+        }
+        Some(Self {
+            par_time, par_score, ball_speed, background,
+            hint: hint.iter().map(|b| *b as char).collect(),
+        })
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MotionAxis {
+    None, Horizontal, Vertical,
+}
+
+// How a row of tiles swings back and forth. Rows start where they were placed, and go 'distance' either side of where they'd be if 'phase' was 0
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct RowMotion {
+    pub axis: MotionAxis,
+    // Pixels
+    pub distance: u8,
+    // Seconds to swing there and back
+    pub period: f32,
+    // How far through a swing it starts, 0.0 to 1.0
+    pub phase: f32,
+}
+
+impl Default for RowMotion {
+    fn default() -> Self {
+        Self { axis: MotionAxis::None, distance: 12, period: 2.0, phase: 0.0 }
+    }
+}
+
+impl RowMotion {
+    pub fn moving(&self) -> bool {
+        self.axis != MotionAxis::None && self.period > 0.0
+    }
+
+    fn along_axis(&self, amount: f32) -> Vec2 {
+        match self.axis {
+            MotionAxis::None => Vec2::ZERO,
+            MotionAxis::Horizontal => vec2(amount, 0.0),
+            MotionAxis::Vertical => vec2(0.0, amount),
+        }
+    }
+    // How far the row has moved from where it was placed after 'time' seconds
+    pub fn offset(&self, time: f32) -> Vec2 {
+        if !self.moving() {
+            return Vec2::ZERO;
+        }
+        let swing = |t: f32| (TAU * (t / self.period + self.phase)).sin();
+        self.along_axis((swing(time) - swing(0.0)) * self.distance as f32)
+    }
+    // Pixels per second
+    pub fn velocity(&self, time: f32) -> Vec2 {
+        if !self.moving() {
+            return Vec2::ZERO;
+        }
+        self.along_axis((TAU * (time / self.period + self.phase)).cos() * TAU / self.period * self.distance as f32)
+    }
+
+    pub fn write(&self, w: &mut ByteWriter) {
+        w.u8(self.axis as u8);
+        w.u8(self.distance);
+        w.f32(self.period);
+        w.f32(self.phase);
+    }
+    pub fn read(r: &mut ByteReader) -> Option<Self> {
+        let axis = match r.u8()? {
+            0 => MotionAxis::None,
+            1 => MotionAxis::Horizontal,
+            2 => MotionAxis::Vertical,
+            _ => return None,
+        };
+        let (distance, period, phase) = (r.u8()?, r.f32()?, r.f32()?);
+        match period.is_finite() && period > 0.0 && (0.0..=1.0).contains(&phase) {
+            true => Some(Self { axis, distance, period, phase }),
+            false => None,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq)]
 pub struct Level {
     tiles: TileArray,
+    hit_points: HitPointArray,
+    powerups: PowerupArray,
+    width: usize,
+    height: usize,
     name: String,
+    meta: LevelMeta,
+    motions: [RowMotion; LEVEL_HEIGHT],
+    // How long the level's been going, for moving rows. Always 0 in the editor, so everything's where it was placed
+    time: f32,
+}
+
+impl Default for Level {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Level {
     pub fn new() -> Self {
         Self {
             tiles: [Tile::Air; LEVEL_WIDTH*LEVEL_HEIGHT],
+            hit_points: [0; LEVEL_WIDTH*LEVEL_HEIGHT],
+            powerups: [PlacedPowerup::None; LEVEL_WIDTH*LEVEL_HEIGHT],
+            width: LEVEL_WIDTH,
+            height: LEVEL_HEIGHT,
             name: String::new(),
+            meta: LevelMeta::default(),
+            motions: [RowMotion::default(); LEVEL_HEIGHT],
+            time: 0.0,
         }
     }
 
@@ -95,27 +267,182 @@ impl Level {
     pub fn tiles_mut(&mut self) -> &mut TileArray {
         &mut self.tiles
     }
+    pub fn hit_points(&self) -> &HitPointArray {
+        &self.hit_points
+    }
+    pub fn hit_points_mut(&mut self) -> &mut HitPointArray {
+        &mut self.hit_points
+    }
+    pub fn powerups(&self) -> &PowerupArray {
+        &self.powerups
+    }
+    pub fn powerups_mut(&mut self) -> &mut PowerupArray {
+        &mut self.powerups
+    }
+    pub fn width(&self) -> usize {
+        self.width
+    }
+    pub fn height(&self) -> usize {
+        self.height
+    }
+    // Anything that ends up outside of the level is cleared
+    pub fn set_size(&mut self, width: usize, height: usize) {
+        self.width = width.clamp(1, LEVEL_WIDTH);
+        self.height = height.clamp(1, LEVEL_HEIGHT);
+        for i in 0..self.tiles.len() {
+            if i % LEVEL_WIDTH >= self.width || i / LEVEL_WIDTH >= self.height {
+                self.tiles[i] = Tile::Air;
+                self.hit_points[i] = 0;
+                self.powerups[i] = PlacedPowerup::None;
+            }
+        }
+        for m in &mut self.motions[self.height..] {
+            *m = RowMotion::default();
+        }
+    }
     pub fn name(&self) -> &String {
         &self.name
     }
     pub fn name_mut(&mut self) -> &mut String {
         &mut self.name
     }
+    pub fn meta(&self) -> &LevelMeta {
+        &self.meta
+    }
+    pub fn meta_mut(&mut self) -> &mut LevelMeta {
+        &mut self.meta
+    }
+    pub fn motions(&self) -> &[RowMotion; LEVEL_HEIGHT] {
+        &self.motions
+    }
+    pub fn motions_mut(&mut self) -> &mut [RowMotion; LEVEL_HEIGHT] {
+        &mut self.motions
+    }
+    pub fn moving(&self) -> bool {
+        self.motions.iter().any(|m| m.moving())
+    }
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+    pub fn set_time(&mut self, time: f32) {
+        self.time = time;
+    }
+
+    pub fn write_snapshot(&self, w: &mut ByteWriter) {
+        let name = self.name.as_bytes();
+        for i in 0..LEVEL_NAME_LEN {
+            w.u8(*name.get(i).unwrap_or(&0xFF));
+        }
+        w.u8(self.width as u8);
+        w.u8(self.height as u8);
+        for t in self.tiles {
+            w.u8(t as u8);
+        }
+        w.bytes(&self.hit_points);
+        for p in self.powerups {
+            w.u8(p.into());
+        }
+        self.meta.write(w);
+        for m in &self.motions {
+            m.write(w);
+        }
+        w.f32(self.time);
+    }
+    pub fn read_snapshot(r: &mut ByteReader) -> Option<Self> {
+        let mut level = Level::new();
+        level.name = r.bytes(LEVEL_NAME_LEN)?
+            .iter()
+            .map(|b| *b as char)
+            .take_while(|c| char_valid(*c))
+            .collect();
+        (level.width, level.height) = (r.u8()? as usize, r.u8()? as usize);
+        if !(1..=LEVEL_WIDTH).contains(&level.width) || !(1..=LEVEL_HEIGHT).contains(&level.height) {
+            return None;
+        }
+        for t in level.tiles.iter_mut() {
+            *t = Tile::try_from(r.u8()?).ok()?;
+        }
+        level.hit_points = r.array()?;
+        for p in level.powerups.iter_mut() {
+            *p = PlacedPowerup::try_from(r.u8()?).ok()?;
+        }
+        level.meta = LevelMeta::read(r)?;
+        for m in level.motions.iter_mut() {
+            *m = RowMotion::read(r)?;
+        }
+        level.time = r.f32()?;
+        Some(level)
+    }
 
+    // Tiles with hit points left lose one instead of being hit
     pub fn break_tile(&mut self, index: usize) -> bool {
-        self.tiles.get_mut(index).is_some_and(|t| t.hit())
+        if !self.tiles.get(index).is_some_and(|t| *t != Tile::Air) {
+            return false;
+        }
+        if self.hit_points[index] > 1 {
+            self.hit_points[index] -= 1;
+            return false;
+        }
+        self.tiles[index].hit()
     }
 
-    pub fn tile_pos(index: usize) -> Vec2 {
-        let (x, y) = (
-            index % LEVEL_WIDTH,
-            index / LEVEL_WIDTH + LEVEL_HEIGHT_PADDING_TOP
-        );
-        vec2(x as f32, y as f32) * (vec2(TILE_WIDTH, TILE_HEIGHT) + TILE_GAP)
+    // Portals pair up in reading order, the first A with the first B, the second A with the second B and so on.
+    // None if it's not a portal or there aren't enough of the other kind
+    pub fn portal_partner(&self, index: usize) -> Option<usize> {
+        let (tile, partner) = match self.tiles.get(index)? {
+            Tile::PortalA => (Tile::PortalA, Tile::PortalB),
+            Tile::PortalB => (Tile::PortalB, Tile::PortalA),
+            _ => return None,
+        };
+        let nth = self.tiles[..index].iter().filter(|t| **t == tile).count();
+        self.tiles.iter().enumerate().filter(|(_, t)| **t == partner).nth(nth).map(|(i, _)| i)
+    }
+    pub fn portals_paired(&self) -> bool {
+        let count = |tile| self.tiles.iter().filter(|t| **t == tile).count();
+        count(Tile::PortalA) == count(Tile::PortalB)
+    }
+
+    // The powerup placed under a tile, which only drops once
+    pub fn take_powerup(&mut self, index: usize) -> PlacedPowerup {
+        self.powerups.get_mut(index).map(|p| std::mem::replace(p, PlacedPowerup::None)).unwrap_or(PlacedPowerup::None)
     }
 
-    pub fn tile_rect(index: usize) -> Rect {
-        let pos = Level::tile_pos(index);
+    // Tiles the level didn't set hit points for take however many their material says
+    pub fn apply_material_hit_points(&mut self, materials: &MaterialTable) {
+        for (t, hp) in self.tiles.iter().zip(self.hit_points.iter_mut()) {
+            if *hp == 0 {
+                *hp = materials[*t as usize].hit_points.max(1);
+            }
+        }
+    }
+
+    // Where the top left tile goes
+    pub fn origin(&self) -> Vec2 {
+        let cell = vec2(TILE_WIDTH, TILE_HEIGHT) + TILE_GAP;
+        let spare = vec2((LEVEL_WIDTH - self.width) as f32, (LEVEL_HEIGHT - self.height) as f32) * cell;
+        (spare / 2.0).floor() + vec2(0.0, LEVEL_HEIGHT_PADDING_TOP as f32 * cell.y)
+    }
+    // The space the level's tiles take up
+    pub fn area_rect(&self) -> Rect {
+        let size = vec2(self.width as f32, self.height as f32) * (vec2(TILE_WIDTH, TILE_HEIGHT) + TILE_GAP) - TILE_GAP;
+        Rect::new(self.origin().x, self.origin().y, size.x, size.y)
+    }
+
+    // Moving rows are wherever they've got to
+    pub fn tile_pos(&self, index: usize) -> Vec2 {
+        let (x, y) = (index % LEVEL_WIDTH, index / LEVEL_WIDTH);
+        self.origin() + vec2(x as f32, y as f32) * (vec2(TILE_WIDTH, TILE_HEIGHT) + TILE_GAP) + self.row_offset(y)
+    }
+    pub fn row_offset(&self, row: usize) -> Vec2 {
+        self.motions.get(row).map_or(Vec2::ZERO, |m| m.offset(self.time))
+    }
+    // How fast a tile's moving, in pixels per second
+    pub fn tile_velocity(&self, index: usize) -> Vec2 {
+        self.motions.get(index / LEVEL_WIDTH).map_or(Vec2::ZERO, |m| m.velocity(self.time))
+    }
+
+    pub fn tile_rect(&self, index: usize) -> Rect {
+        let pos = self.tile_pos(index);
         Rect::new(
             pos.x,
             pos.y,
@@ -124,22 +451,298 @@ impl Level {
         )
     }
 
+    // The cell of the grid that a point is in, which may be outside of the level. This is where tiles were placed, moving rows aren't taken into account
+    fn cell_at(&self, pos: Vec2) -> (i32, i32) {
+        let cell = (pos - self.origin()) / (vec2(TILE_WIDTH, TILE_HEIGHT) + TILE_GAP);
+        (cell.x.floor() as i32, cell.y.floor() as i32)
+    }
+    fn cell_index(&self, x: i32, y: i32) -> Option<usize> {
+        match x >= 0 && y >= 0 && x < self.width as i32 && y < self.height as i32 {
+            true => Some(y as usize * LEVEL_WIDTH + x as usize),
+            false => None,
+        }
+    }
+    // The tile a point is over, if it's in the level
+    pub fn tile_index_at(&self, pos: Vec2) -> Option<usize> {
+        let (x, y) = self.cell_at(pos);
+        self.cell_index(x, y)
+    }
+
+    // The (up to) 8 tiles surrounding a tile
+    pub fn neighbours(&self, index: usize) -> impl Iterator<Item = usize> + '_ {
+        let (x, y) = ((index % LEVEL_WIDTH) as i32, (index / LEVEL_WIDTH) as i32);
+        (-1..=1)
+            .flat_map(move |dy| (-1..=1).map(move |dx| (dx, dy)))
+            .filter(|d| *d != (0, 0))
+            .filter_map(move |(dx, dy)| self.cell_index(x + dx, y + dy))
+    }
+
+    // Every tile that isn't air overlapping 'rect', only looking at the cells the rect covers rather than the whole level.
+    // Moving rows could be anywhere, so then every row is checked, with the rect moved back by however far that row has moved
+    pub fn tiles_in_rect(&self, rect: Rect) -> impl Iterator<Item = usize> + '_ {
+        let rows = match self.moving() {
+            true => 0..=self.height as i32 - 1,
+            false => self.cell_at(rect.point()).1..=self.cell_at(rect.point() + rect.size()).1,
+        };
+        rows
+            .flat_map(move |y| {
+                let rect = rect.offset(-self.row_offset(y.max(0) as usize));
+                let (x0, y0) = self.cell_at(rect.point());
+                let (x1, y1) = self.cell_at(rect.point() + rect.size());
+                let covered = (y0..=y1).contains(&y);
+                (x0..=x1).filter(move |_| covered).filter_map(move |x| self.cell_index(x, y))
+            })
+            .filter(move |i| self.tiles[*i] != Tile::Air && self.tile_rect(*i).overlaps(&rect))
+    }
+
+    // Every tile that isn't air touched by the line from 'from' to 'to', in the order they're touched.
+    // Only the cells the line passes through are looked at, unless rows are moving and the grid can't be followed
+    pub fn tiles_on_segment(&self, from: Vec2, to: Vec2) -> Vec<usize> {
+        if self.moving() {
+            let mut tiles: Vec<(usize, f32)> = self.tiles_in_rect(Rect::new(from.x.min(to.x), from.y.min(to.y), (to.x - from.x).abs(), (to.y - from.y).abs()))
+                .filter_map(|i| Some((i, segment_entry(from, to - from, self.tile_rect(i))?)))
+                .collect();
+            tiles.sort_by(|a, b| a.1.total_cmp(&b.1));
+            return tiles.into_iter().map(|(i, _)| i).collect();
+        }
+
+        let mut tiles = Vec::new();
+        let (mut x, mut y) = self.cell_at(from);
+        let (end_x, end_y) = self.cell_at(to);
+
+        // Step from cell to cell, always crossing whichever cell edge the line reaches first
+        let cell = vec2(TILE_WIDTH, TILE_HEIGHT) + TILE_GAP;
+        let dir = to - from;
+        let step = (dir.x.signum() as i32, dir.y.signum() as i32);
+        let next_edge = |c: i32, s: i32, size: f32, offset: f32| (c + s.max(0)) as f32 * size + offset;
+        let origin = self.origin();
+        let mut t_max = vec2(
+            if dir.x != 0.0 { (next_edge(x, step.0, cell.x, origin.x) - from.x) / dir.x } else { f32::INFINITY },
+            if dir.y != 0.0 { (next_edge(y, step.1, cell.y, origin.y) - from.y) / dir.y } else { f32::INFINITY },
+        );
+        let t_delta = (cell / dir).abs();
+
+        loop {
+            if let Some(i) = self.cell_index(x, y) {
+                if self.tiles[i] != Tile::Air && segment_entry(from, dir, self.tile_rect(i)).is_some() {
+                    tiles.push(i);
+                }
+            }
+            if (x, y) == (end_x, end_y) || t_max.min_element() > 1.0 {
+                break;
+            }
+            if t_max.x < t_max.y {
+                x += step.0;
+                t_max.x += t_delta.x;
+            } else {
+                y += step.1;
+                t_max.y += t_delta.y;
+            }
+        }
+        tiles
+    }
+
     pub fn view_size() -> Vec2 {
         vec2(LEVEL_WIDTH as f32, (LEVEL_HEIGHT + LEVEL_HEIGHT_PADDING_TOP + LEVEL_HEIGHT_PADDING_BOTTOM) as f32) * (vec2(TILE_WIDTH, TILE_HEIGHT) + TILE_GAP)
     }
 
+    #[cfg(feature = "graphics")]
     pub fn draw(&self, texture: &Texture2D) {
         for (i, t) in self.tiles.iter().enumerate() {
             if *t == Tile::Air {
                 continue;
             }
-            let tile_pos = Level::tile_pos(i);
+            let tile_pos = self.tile_pos(i);
             let tile_rect = t.texture_rect();
 
             draw_texture_ex(texture, tile_pos.x, tile_pos.y, WHITE, DrawTextureParams {
                 source: Some(tile_rect),
                 ..Default::default()
             });
+
+            // How many hits are left, on a dark patch so it shows up on any colour
+            let hit_points = self.hit_points[i];
+            if hit_points > 1 {
+                draw_rectangle(tile_pos.x + 2.0, tile_pos.y, 7.0, TILE_HEIGHT, Color::from_rgba(0, 0, 0, 140));
+                render_text(&hit_points.to_string(), tile_pos + vec2(3.0, 0.0), WHITE, TextAlign::Left, texture);
+            }
         }
     }
-}
\ No newline at end of file
+    // Only the tiles in moving rows
+    #[cfg(feature = "graphics")]
+    pub fn draw_moving(&self, texture: &Texture2D, color: Color) {
+        for (i, t) in self.tiles.iter().enumerate() {
+            if *t == Tile::Air || !self.motions[i / LEVEL_WIDTH].moving() {
+                continue;
+            }
+            let tile_pos = self.tile_pos(i);
+            draw_texture_ex(texture, tile_pos.x, tile_pos.y, color, DrawTextureParams {
+                source: Some(t.texture_rect()),
+                ..Default::default()
+            });
+        }
+    }
+}
+
+// How far along the line from 'from' to 'from + dir' it first touches 'rect', if it does at all
+fn segment_entry(from: Vec2, dir: Vec2, rect: Rect) -> Option<f32> {
+    let (mut entry, mut exit) = (0.0_f32, 1.0_f32);
+    for axis in 0..2 {
+        let (min, max) = (rect.point()[axis], rect.point()[axis] + rect.size()[axis]);
+        if dir[axis] == 0.0 {
+            if from[axis] < min || from[axis] > max {
+                return None;
+            }
+            continue;
+        }
+        let (a, b) = ((min - from[axis]) / dir[axis], (max - from[axis]) / dir[axis]);
+        entry = entry.max(a.min(b));
+        exit = exit.min(a.max(b));
+    }
+    match entry <= exit {
+        true => Some(entry),
+        false => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::world::rng::Rng;
+
+    // A smaller level than usual so it doesn't start at the top left, with a few gaps of air
+    fn test_level() -> Level {
+        let mut level = Level::new();
+        level.set_size(12, 16);
+        let mut rng = Rng::new(8);
+        for i in 0..level.tiles().len() {
+            if i % LEVEL_WIDTH < level.width() && i / LEVEL_WIDTH < level.height() && rng.gen_range(0, 4) != 0 {
+                level.tiles_mut()[i] = Tile::Stone;
+            }
+        }
+        level
+    }
+
+    // Every tile checked, clipping the line to each tile's rect
+    fn brute_force(level: &Level, from: Vec2, to: Vec2) -> Vec<(usize, f32)> {
+        let mut tiles = Vec::new();
+        for i in 0..level.tiles().len() {
+            if level.tiles()[i] == Tile::Air {
+                continue;
+            }
+            let rect = level.tile_rect(i);
+            let dir = to - from;
+            let (mut t0, mut t1) = (0.0_f32, 1.0_f32);
+            let edges = [(-dir.x, from.x - rect.left()), (dir.x, rect.right() - from.x), (-dir.y, from.y - rect.top()), (dir.y, rect.bottom() - from.y)];
+            let touches = edges.iter().all(|(p, q)| match (*p == 0.0, *p < 0.0) {
+                (true, _) => *q >= 0.0,
+                (false, true) => { t0 = t0.max(q / p); true }
+                (false, false) => { t1 = t1.min(q / p); true }
+            });
+            if touches && t0 <= t1 {
+                tiles.push((i, t0));
+            }
+        }
+        tiles
+    }
+
+    fn check(level: &Level, from: Vec2, to: Vec2) {
+        let found = level.tiles_on_segment(from, to);
+        let expected = brute_force(level, from, to);
+
+        let mut sorted = found.clone();
+        sorted.sort();
+        assert_eq!(sorted, expected.iter().map(|(i, _)| *i).collect::<Vec<_>>(), "{from} to {to}");
+
+        let entry = |i: &usize| expected.iter().find(|(j, _)| j == i).unwrap().1;
+        assert!(found.windows(2).all(|w| entry(&w[0]) <= entry(&w[1])), "{from} to {to} out of order: {found:?}");
+    }
+
+    fn random_segments(seed: u64, length: f32) -> Vec<(Vec2, Vec2)> {
+        let mut rng = Rng::new(seed);
+        let view_size = Level::view_size();
+        (0..2000)
+            .map(|_| {
+                let from = vec2(rng.gen_range(-20.0, view_size.x + 20.0), rng.gen_range(-20.0, view_size.y + 20.0));
+                (from, from + Vec2::from_angle(rng.gen_range(0.0, TAU)) * rng.gen_range(0.0, length))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn segment_horizontal() {
+        let level = test_level();
+        let (origin, area) = (level.origin(), level.area_rect());
+        for y in [0.0, 0.5, TILE_HEIGHT, TILE_HEIGHT + 0.5, 20.0, 33.3, area.h] {
+            check(&level, vec2(area.left() + 3.0, origin.y + y), vec2(area.right() - 3.0, origin.y + y));
+            check(&level, vec2(area.right() - 3.0, origin.y + y), vec2(area.left() + 3.0, origin.y + y));
+        }
+    }
+
+    #[test]
+    fn segment_vertical() {
+        let level = test_level();
+        let (origin, area) = (level.origin(), level.area_rect());
+        for x in [0.0, 0.5, TILE_WIDTH, TILE_WIDTH + 0.5, 12.0, 50.7, area.w] {
+            check(&level, vec2(origin.x + x, area.top() + 2.0), vec2(origin.x + x, area.bottom() - 2.0));
+            check(&level, vec2(origin.x + x, area.bottom() - 2.0), vec2(origin.x + x, area.top() + 2.0));
+        }
+    }
+
+    #[test]
+    fn segment_diagonal() {
+        let level = test_level();
+        let area = level.area_rect();
+        check(&level, area.point(), area.point() + area.size());
+        check(&level, area.point() + area.size(), area.point());
+        check(&level, vec2(area.right(), area.top()), vec2(area.left(), area.bottom()));
+        for (from, to) in random_segments(1, 60.0) {
+            check(&level, from, to);
+        }
+    }
+
+    #[test]
+    fn segment_zero_length() {
+        let level = test_level();
+        let origin = level.origin();
+        for pos in [origin, origin + vec2(3.0, 3.0), origin + vec2(TILE_WIDTH + 0.5, 0.0), origin + vec2(0.0, TILE_HEIGHT), origin - 1.0] {
+            check(&level, pos, pos);
+        }
+        for (from, _) in random_segments(2, 0.0) {
+            check(&level, from, from);
+        }
+    }
+
+    #[test]
+    fn segment_from_outside() {
+        let level = test_level();
+        let area = level.area_rect();
+        check(&level, vec2(-50.0, area.center().y), vec2(area.center().x, area.center().y));
+        check(&level, vec2(area.center().x, -50.0), area.center());
+        check(&level, vec2(area.right() + 50.0, area.bottom() + 50.0), area.center());
+        check(&level, vec2(-50.0, -50.0), Level::view_size() + 50.0);
+        for (from, to) in random_segments(3, 300.0) {
+            check(&level, from, to);
+        }
+    }
+
+    #[test]
+    fn segment_moving_rows() {
+        let mut level = test_level();
+        for (y, m) in level.motions_mut().iter_mut().enumerate().take(16) {
+            m.axis = match y % 3 {
+                0 => MotionAxis::None,
+                1 => MotionAxis::Horizontal,
+                _ => MotionAxis::Vertical,
+            };
+            m.distance = 20;
+            m.phase = y as f32 / 16.0;
+        }
+        for time in [0.0, 0.3, 1.1] {
+            level.set_time(time);
+            for (from, to) in random_segments(4, 100.0) {
+                check(&level, from, to);
+            }
+        }
+    }
+}
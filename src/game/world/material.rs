@@ -0,0 +1,79 @@
+use crate::game::bytes::{ByteReader, ByteWriter};
+
+use super::level::{Tile, TILE_COUNT};
+
+// What's doing the hitting, since some tiles only break to some things
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum HitSource {
+    Ball, Bullet,
+    // Blasts from explosive tiles, zaps and fireballs count as a ball hit
+    Explosion, Zap,
+    // Fireballs break whatever they go through in one go
+    Fireball,
+}
+
+// How a kind of tile behaves in the game
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Material {
+    pub ball_breakable: bool,
+    pub bullet_breakable: bool,
+    // How many hits a tile takes, for any tile the level itself leaves at 1
+    pub hit_points: u8,
+    pub score: usize,
+    // How much breaking it counts down towards the next powerup, 0 means it never drops one
+    pub drop_weight: f32,
+}
+
+pub type MaterialTable = [Material; TILE_COUNT];
+
+impl Material {
+    pub const fn breakable(score: usize) -> Self {
+        Self { ball_breakable: true, bullet_breakable: true, hit_points: 1, score, drop_weight: 1.0 }
+    }
+    pub const fn solid() -> Self {
+        Self { ball_breakable: false, bullet_breakable: false, hit_points: 1, score: 0, drop_weight: 0.0 }
+    }
+
+    pub fn breakable_by(&self, source: HitSource) -> bool {
+        match source {
+            HitSource::Ball | HitSource::Explosion | HitSource::Zap | HitSource::Fireball => self.ball_breakable,
+            HitSource::Bullet => self.bullet_breakable,
+        }
+    }
+
+    pub fn write_snapshot(&self, w: &mut ByteWriter) {
+        w.bool(self.ball_breakable);
+        w.bool(self.bullet_breakable);
+        w.u8(self.hit_points);
+        w.usize(self.score);
+        w.f32(self.drop_weight);
+    }
+    pub fn read_snapshot(r: &mut ByteReader) -> Option<Self> {
+        Some(Self {
+            ball_breakable: r.bool()?,
+            bullet_breakable: r.bool()?,
+            hit_points: r.u8()?,
+            score: r.usize()?,
+            drop_weight: r.f32()?,
+        })
+    }
+}
+
+// Every tile is worth the same and can be broken by anything, apart from metal, gold, portals and air of course
+pub fn default_materials() -> MaterialTable {
+    let mut materials = [Material::breakable(10); TILE_COUNT];
+    for tile in [Tile::Metal, Tile::Gold, Tile::Air, Tile::PortalA, Tile::PortalB] {
+        materials[tile as usize] = Material::solid();
+    }
+    materials
+}
+
+pub fn write_materials(materials: &MaterialTable, w: &mut ByteWriter) {
+    for m in materials {
+        m.write_snapshot(w);
+    }
+}
+pub fn read_materials(r: &mut ByteReader) -> Option<MaterialTable> {
+    let materials: Vec<Material> = (0..TILE_COUNT).map(|_| Material::read_snapshot(r)).collect::<Option<_>>()?;
+    materials.try_into().ok()
+}
@@ -1,10 +1,21 @@
-use ball::{Ball, BallHitState, BALL_SIZE, BALL_TEXTURE};
+use ball::{Ball, BallHitState};
+#[cfg(feature = "graphics")]
+use ball::{BALL_SIZE, BALL_TEXTURE};
 use bullet::{Bullet, BulletHitState};
-use level::Level;
-use macroquad::{color::{Color, WHITE}, color_u8, input::{is_key_pressed, KeyCode}, math::{vec2, Rect}, rand::{gen_range, ChooseRandom}, texture::{draw_texture_ex, DrawTextureParams, Texture2D}, window::clear_background};
+use level::{Level, Tile};
+use material::HitSource;
+use input::InputFrame;
+use macroquad::{color::Color, color_u8, math::{vec2, Rect, Vec2}};
+#[cfg(feature = "graphics")]
+use macroquad::{color::WHITE, shapes::{draw_line, draw_rectangle}, texture::{draw_texture_ex, DrawTextureParams, Texture2D}, window::clear_background};
 use paddle::Paddle;
-use powerup::{Powerup, PowerupHitState, PowerupKind};
+use powerup::{PlacedPowerup, Powerup, PowerupHitState, PowerupKind};
+use rng::Rng;
+use rules::GameRules;
 
+use super::bytes::{ByteReader, ByteWriter};
+
+#[cfg(feature = "graphics")]
 use crate::text_renderer::{render_text, TextAlign};
 
 pub mod paddle;
@@ -12,67 +23,136 @@ pub mod ball;
 pub mod powerup;
 pub mod bullet;
 pub mod level;
+pub mod input;
+pub mod rng;
+pub mod rules;
+pub mod material;
 
 pub const CARRY_ICON_TEXTURE: Rect = Rect { x: 118.0, y: 8.0, w: 4.0, h: 4.0 };
 pub const SAFE_TEXTURE: Rect = Rect { x: 157.0, y: 18.0, w: 1.0, h: 6.0 };
+pub const MAGNET_ICON_TEXTURE: Rect = Rect { x: 132.0, y: 31.0, w: 4.0, h: 4.0 };
 pub const BG_COL: Color = color_u8!(25, 31, 58, 255);
+// How long a zap's bolts hang around for
+pub const ZAP_BOLT_TIME: f32 = 0.3;
+
+// The world always steps by the same amount of time, so the same inputs always give the same game
+pub const TICK_RATE: f32 = 120.0;
+pub const TICK_DELTA: f32 = 1.0 / TICK_RATE;
 
 pub enum Lives {
     Default, Some(usize), Infinite,
 }
 
-#[derive(PartialEq, Eq)]
-pub enum WorldUpdateReturn {
+// Everything notable that happened during a tick, in the order it happened.
+// Positions are in world space, so sounds, particles, stats etc. can all be driven from these without touching the world itself
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum WorldEvent {
+    // A tile was hit but not broken (e.g. stone cracking)
+    TileHit { index: usize, pos: Vec2, tile: Tile },
+    TileBroken { index: usize, pos: Vec2, tile: Tile, score: usize },
+    // An explosive tile's blast went off, hitting the tiles around it
+    TileExploded { index: usize, pos: Vec2 },
+    // A zap went off from the paddle at 'pos', hitting 'tiles' tiles and scoring 'score' from the ones it broke
+    Zapped { pos: Vec2, tiles: usize, score: usize },
+    PowerupSpawned { kind: PowerupKind, pos: Vec2 },
+    PowerupCollected { kind: PowerupKind, pos: Vec2, score: usize },
+    BallLost { pos: Vec2 },
+    LifeLost { lives_left: usize },
+    LifeGained { lives: usize },
+    CarryUsed { pos: Vec2 },
+    // The magnet caught a ball
+    BallCaught { pos: Vec2 },
+    BulletFired { pos: Vec2 },
     BallStuck,
     GameOver,
-    None,
 }
 
 pub struct World {
     level: Level,
+    // Which level of the pack this is, counting from 0. Later levels drop more bad powerups
+    level_number: usize,
     paddle: Paddle,
     lives: Option<usize>,
     balls:    Vec<Ball>,
     powerups: Vec<Powerup>,
     bullets:  Vec<Bullet>,
     score: usize,
+    // The score the level was started with, so the level's own score can be compared against its par
+    start_score: usize,
+    // Only counts up once the first ball has been launched
+    time: f32,
+    launched: bool,
+    events: Vec<WorldEvent>,
 
     balls_to_dispense: Vec<(f32, Vec<Ball>)>,
+    // Explosive tiles that have broken, and how long until they go off
+    explosions: Vec<(usize, f32)>,
+    // Zap bolts being drawn, from and to, and how long they've got left
+    bolts: Vec<(Vec2, Vec2, f32)>,
 
     ball_stuck_timer: f32,
-    next_powerup: usize,
+    // Counts down by the drop weight of each tile broken
+    next_powerup: f32,
+
+    rng: Rng,
+    rules: GameRules,
 }
 
 impl World {
-    pub fn new(level: Level, score: Option<usize>, paddle_pos: Option<f32>, lives: Lives, carries: Option<usize>) -> Self {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(mut level: Level, level_number: usize, score: Option<usize>, paddle_pos: Option<f32>, lives: Lives, carries: Option<usize>, seed: u64, rules: GameRules) -> Self {
         let lives = match lives {
-            Lives::Default => Some(2),
+            Lives::Default => Some(rules.lives),
             Lives::Some(l) => Some(l),
             Lives::Infinite => None
         };
+        let mut rng = Rng::new(seed);
+        level.apply_material_hit_points(&rules.materials);
 
         Self {
             level,
-            paddle: Paddle::new(paddle_pos, carries),
+            level_number,
+            paddle: Paddle::new(paddle_pos, carries, &rules),
             lives,
             balls:    Vec::with_capacity(100),
             powerups: Vec::with_capacity(20),
             bullets:  Vec::with_capacity(20),
             score: score.unwrap_or(0),
+            start_score: score.unwrap_or(0),
+            time: 0.0,
+            launched: false,
+            events: Vec::with_capacity(20),
             balls_to_dispense: Vec::with_capacity(5),
+            explosions: Vec::new(),
+            bolts: Vec::new(),
 
             ball_stuck_timer: 0.0,
-            next_powerup: gen_range(0, 5),
+            next_powerup: rng.gen_range(rules.first_powerup_gap.0, rules.first_powerup_gap.1) as f32,
+
+            rng,
+            rules,
         }
     }
 
     pub fn level_complete(&self) -> bool {
-        !self.level.tiles().iter().any(|t| t.breakable())
+        !self.level.tiles().iter().any(|t| self.rules.material(*t).ball_breakable)
     }
 
     pub fn score(&self) -> usize {
         self.score
     }
+    pub fn level_score(&self) -> usize {
+        self.score - self.start_score
+    }
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+    pub fn launched(&self) -> bool {
+        self.launched
+    }
+    pub fn level(&self) -> &Level {
+        &self.level
+    }
     pub fn paddle_pos(&self) -> f32 {
         self.paddle.x()
     }
@@ -85,24 +165,124 @@ impl World {
     pub fn carries(&self) -> usize {
         self.paddle.carries()
     }
+    pub fn seed(&self) -> u64 {
+        self.rng.seed()
+    }
+    pub fn rules(&self) -> &GameRules {
+        &self.rules
+    }
+    pub fn paddle(&self) -> &Paddle {
+        &self.paddle
+    }
+    pub fn balls(&self) -> &Vec<Ball> {
+        &self.balls
+    }
 
-    pub fn break_tile(&mut self, index: usize) {
-        if !self.level.break_tile(index) {
-            return;
+    // Everything about the world, so it can be saved and picked back up exactly where it was left
+    pub fn write_snapshot(&self, w: &mut ByteWriter) {
+        self.level.write_snapshot(w);
+        w.usize(self.level_number);
+        self.paddle.write_snapshot(w);
+        w.option(self.lives, ByteWriter::usize);
+        w.list(&self.balls, |w, b| b.write_snapshot(w));
+        w.list(&self.powerups, |w, p| p.write_snapshot(w));
+        w.list(&self.bullets, |w, b| b.write_snapshot(w));
+        w.usize(self.score);
+        w.usize(self.start_score);
+        w.f32(self.time);
+        w.bool(self.launched);
+        w.list(&self.balls_to_dispense, |w, (t, balls)| {
+            w.f32(*t);
+            w.list(balls, |w, b| b.write_snapshot(w));
+        });
+        w.list(&self.explosions, |w, (index, t)| {
+            w.usize(*index);
+            w.f32(*t);
+        });
+        w.list(&self.bolts, |w, (from, to, t)| {
+            w.vec2(*from);
+            w.vec2(*to);
+            w.f32(*t);
+        });
+        w.f32(self.ball_stuck_timer);
+        w.f32(self.next_powerup);
+        self.rng.write_snapshot(w);
+        self.rules.write_snapshot(w);
+    }
+    pub fn read_snapshot(r: &mut ByteReader) -> Option<Self> {
+        Some(Self {
+            level: Level::read_snapshot(r)?,
+            level_number: r.usize()?,
+            paddle: Paddle::read_snapshot(r)?,
+            lives: r.option(ByteReader::usize)?,
+            balls:    r.list(Ball::read_snapshot)?,
+            powerups: r.list(Powerup::read_snapshot)?,
+            bullets:  r.list(Bullet::read_snapshot)?,
+            score: r.usize()?,
+            start_score: r.usize()?,
+            time: r.f32()?,
+            launched: r.bool()?,
+            events: Vec::with_capacity(20),
+            balls_to_dispense: r.list(|r| Some((r.f32()?, r.list(Ball::read_snapshot)?)))?,
+            explosions: r.list(|r| Some((r.usize()?, r.f32()?)))?,
+            bolts: r.list(|r| Some((r.vec2()?, r.vec2()?, r.f32()?)))?,
+            ball_stuck_timer: r.f32()?,
+            next_powerup: r.f32()?,
+            rng: Rng::read_snapshot(r)?,
+            rules: GameRules::read_snapshot(r)?,
+        })
+    }
+
+    pub fn break_tile(&mut self, index: usize, source: HitSource) {
+        let (tile, pos) = match self.level.tiles().get(index) {
+            Some(t) if self.rules.material(*t).breakable_by(source) => (*t, self.level.tile_pos(index)),
+            _ => return,
+        };
+        let material = *self.rules.material(tile);
+        let mut broken = self.level.break_tile(index);
+        while source == HitSource::Fireball && !broken && self.level.tiles()[index] != Tile::Air {
+            broken = self.level.break_tile(index);
         }
-        self.score += 10;
-        if self.next_powerup == 0 {
-            self.next_powerup = match self.balls.len() {
-                ..= 3 => gen_range(2,  5),
-                ..=10 => gen_range(4,  8),
-                ..=15 => gen_range(5, 10),
-                _     => gen_range(5, 15),
-            };
-            self.powerups.push(Powerup::new(index, self.paddle.carries() < 3));
+        if !broken {
+            self.events.push(WorldEvent::TileHit { index, pos, tile });
             return;
         }
-        self.next_powerup -= 1;
+        self.score += material.score;
+        self.events.push(WorldEvent::TileBroken { index, pos, tile, score: material.score });
+        if tile == Tile::Explosive {
+            self.explosions.push((index, self.rules.explosion_delay));
+        }
+        // Any broken tile means the ball isn't stuck, whether or not something drops out of it
         self.ball_stuck_timer = 0.0;
+        // Powerups placed by the level always drop, and don't count towards the random ones
+        match self.level.take_powerup(index) {
+            PlacedPowerup::None => {}
+            PlacedPowerup::Random => return self.spawn_powerup(index, None),
+            PlacedPowerup::Kind(kind) => return self.spawn_powerup(index, Some(kind)),
+        }
+        if self.next_powerup <= 0.0 && material.drop_weight > 0.0 {
+            let (min, max) = self.rules.powerup_gap(self.balls.len());
+            self.next_powerup = self.rng.gen_range(min, max) as f32;
+            self.spawn_powerup(index, None);
+            return;
+        }
+        self.next_powerup -= material.drop_weight;
+    }
+
+    // A random kind if 'kind' is None
+    fn spawn_powerup(&mut self, tile_index: usize, kind: Option<PowerupKind>) {
+        let powerup = match kind {
+            Some(kind) => Powerup::with_kind(self.level.tile_pos(tile_index), kind, &mut self.rng),
+            None => Powerup::new(
+                self.level.tile_pos(tile_index),
+                self.paddle.carries() < self.rules.max_carries,
+                self.lives.is_some_and(|l| l < self.rules.max_lives),
+                self.rules.negative_powerup_weight(self.level_number),
+                &mut self.rng,
+            ),
+        };
+        self.events.push(WorldEvent::PowerupSpawned { kind: powerup.kind(), pos: powerup.pos() });
+        self.powerups.push(powerup);
     }
 
     pub fn give_free_ball(&mut self) {
@@ -119,15 +299,35 @@ impl World {
                 new_balls.push(Ball::new(b.pos(), b.vel().to_angle(), speed));
             }
         }
-        new_balls.shuffle();
+        self.rng.shuffle(&mut new_balls);
         new_balls.truncate(20);
         self.balls.extend(new_balls);
     }
 
+    // Hits the closest tiles to the middle of the paddle that a ball could break
+    pub fn zap(&mut self) {
+        let from = vec2(self.paddle.x() + self.paddle.width() / 2.0, Paddle::y());
+        let mut targets: Vec<(usize, Vec2)> = self.level.tiles()
+            .iter()
+            .enumerate()
+            .filter(|(_, t)| self.rules.material(**t).breakable_by(HitSource::Zap))
+            .map(|(i, _)| (i, self.level.tile_rect(i).center()))
+            .collect();
+        targets.sort_by(|(_, a), (_, b)| a.distance_squared(from).total_cmp(&b.distance_squared(from)));
+        targets.truncate(self.rules.zap_tiles);
+
+        let score = self.score;
+        for (index, to) in &targets {
+            self.bolts.push((from, *to, ZAP_BOLT_TIME));
+            self.break_tile(*index, HitSource::Zap);
+        }
+        self.events.push(WorldEvent::Zapped { pos: from, tiles: targets.len(), score: self.score - score });
+    }
+
     pub fn dispense_angled_balls(&mut self, amount: usize) {
-        let pos = vec2(self.paddle.x(), Level::view_size().y - gen_range(23.0, 40.0));
-        let rotation = gen_range(-90.0, -75.0);
-        let rotation_step = gen_range(5.0, 15.0);
+        let pos = vec2(self.paddle.x(), Level::view_size().y - self.rng.gen_range(23.0, 40.0));
+        let rotation = self.rng.gen_range(-90.0, -75.0);
+        let rotation_step = self.rng.gen_range(5.0, 15.0);
 
         let mut current_rotation: f32 = rotation;
         let mut balls = Vec::new();
@@ -138,8 +338,12 @@ impl World {
         self.balls_to_dispense.push((f32::INFINITY, balls));
     }
 
-    pub fn update(&mut self) -> WorldUpdateReturn {
-        let delta = macroquad::time::get_frame_time();
+    // Steps the world forward by a single tick, returning everything that happened during it
+    pub fn update(&mut self, input: InputFrame) -> Vec<WorldEvent> {
+        let delta = TICK_DELTA;
+        self.events.clear();
+        // Moving rows move first, and everything else collides with where they've got to
+        self.level.set_time(self.level.time() + delta);
         if !self.paddle.carrying() {
             self.ball_stuck_timer += delta;
         } else {
@@ -150,11 +354,11 @@ impl World {
         {
             let view_size = Level::view_size();
 
-            for (start_x, keycode, dir) in [
-                ( 30.0, KeyCode::Q,  45.0_f32),
-                (110.0, KeyCode::E,  45.0_f32),
+            for (start_x, pressed, dir) in [
+                ( 30.0, input.test_balls_left,  45.0_f32),
+                (110.0, input.test_balls_right, 45.0_f32),
             ] {
-                if is_key_pressed(keycode) {
+                if pressed {
                     for i in 0..5 {
                         self.balls.push(Ball::new(vec2(start_x + i as f32 * 4.0, view_size.y - 50.0), dir.to_degrees(), 1.0));
                     }
@@ -163,9 +367,17 @@ impl World {
         }
 
         // Balls
-        let carried = self.paddle.update(delta, &mut self.bullets);
-        if let Some(carried) = carried {
-            self.balls.push(carried);
+        let bullet_count = self.bullets.len();
+        let carried = self.paddle.update(delta, &input, &mut self.bullets, &self.rules);
+        for b in &self.bullets[bullet_count..] {
+            self.events.push(WorldEvent::BulletFired { pos: b.pos() });
+        }
+        if !carried.is_empty() {
+            self.balls.extend(carried);
+            self.launched = true;
+        }
+        if self.launched && !self.level_complete() {
+            self.time += delta;
         }
 
         for (t, balls) in &mut self.balls_to_dispense {
@@ -181,65 +393,112 @@ impl World {
         self.balls_to_dispense.retain(|(_, b)| !b.is_empty());
 
         let mut hit_tiles = Vec::new();
-        let mut new_carry = None;
+        let mut on_paddle = Vec::new();
         let mut remove_balls = Vec::new();
         for (i, ball) in self.balls.iter_mut().enumerate() {
-            let hit_state = ball.update(delta, &self.paddle, &mut self.level, self.paddle.balls_safe());
+            let hit_state = ball.update(delta, &self.paddle, &self.level, self.paddle.balls_safe(), &mut self.rng, &self.rules);
 
             if hit_state == BallHitState::Floor {
+                self.events.push(WorldEvent::BallLost { pos: ball.pos() });
                 remove_balls.push(i);
             }
             if hit_state == BallHitState::Paddle {
                 self.ball_stuck_timer = 0.0;
-                if new_carry.is_none() {
-                    new_carry = Some(i);
-                }
+                on_paddle.push(i);
             }
             if let BallHitState::Tiles(tiles) = hit_state {
-                hit_tiles.extend(tiles);
+                let source = if self.paddle.fireball() { HitSource::Fireball } else { HitSource::Ball };
+                hit_tiles.extend(tiles.into_iter().map(|t| (t, source)));
             }
         }
 
-        if let Some(new_carry) = new_carry {
-            if self.paddle.can_carry() {
-                self.paddle.carry(self.balls.remove(new_carry));
+        // The magnet catches every ball that touches the paddle, otherwise a carry can catch the first one
+        let caught = match (self.paddle.magnet(), on_paddle.first()) {
+            (true, _) => on_paddle,
+            (false, Some(i)) if self.paddle.can_carry(&input) => vec![*i],
+            _ => vec![],
+        };
+        for i in caught.iter().rev() {
+            let ball = self.balls.remove(*i);
+            match self.paddle.magnet() {
+                true => {
+                    self.events.push(WorldEvent::BallCaught { pos: ball.pos() });
+                    self.paddle.stick(ball);
+                }
+                false => {
+                    self.events.push(WorldEvent::CarryUsed { pos: ball.pos() });
+                    self.paddle.carry(ball);
+                }
             }
         }
+        // The lost balls are taken out later on, so they need to skip over the caught ones
+        for i in &mut remove_balls {
+            *i -= caught.iter().filter(|c| **c < *i).count();
+        }
 
         // Bullets
         let mut remove_bullets = Vec::new();
         for (i, b) in self.bullets.iter_mut().enumerate() {
-            let hit_state = b.update(delta, &mut self.level);
+            let hit_state = b.update(delta, &self.level, &self.rules);
             
             if let BulletHitState::Tile(index) = hit_state {
-                hit_tiles.push(index);
+                hit_tiles.push((index, HitSource::Bullet));
             }
             if hit_state != BulletHitState::None {
                 remove_bullets.push(i);
             }
         }
 
-        for index in hit_tiles {
-            self.break_tile(index);
+        for (index, source) in hit_tiles {
+            self.break_tile(index, source);
+        }
+
+        // Explosions, any explosives they break are added on and go off later, so chains spread out bit by bit
+        for (_, t) in &mut self.explosions {
+            *t -= delta;
+        }
+        let (exploding, waiting): (Vec<_>, Vec<_>) = std::mem::take(&mut self.explosions).into_iter().partition(|(_, t)| *t <= 0.0);
+        self.explosions = waiting;
+        for (index, _) in exploding {
+            self.events.push(WorldEvent::TileExploded { index, pos: self.level.tile_pos(index) });
+            let neighbours: Vec<usize> = self.level.neighbours(index).collect();
+            for neighbour in neighbours {
+                self.break_tile(neighbour, HitSource::Explosion);
+            }
         }
 
         // Powerups
         let mut remove_powerups = Vec::new();
         let mut angled_balls = Vec::new();
         let mut trail = false;
+        let mut zap = false;
         for (i, powerup) in self.powerups.iter_mut().enumerate() {
-            let hit_state = powerup.update(delta, &self.paddle);
+            let hit_state = powerup.update(delta * self.paddle.time_scale(&self.rules), &self.paddle);
 
             if hit_state == PowerupHitState::Paddle {
-                self.score += 15;
+                // No points for catching a bad one
+                let score = if powerup.kind().negative() { 0 } else { self.rules.powerup_score };
+                self.score += score;
+                self.events.push(WorldEvent::PowerupCollected { kind: powerup.kind(), pos: powerup.pos(), score });
                 match powerup.kind() {
-                    PowerupKind::PaddleCarry => self.paddle.powerup_carry(),
-                    PowerupKind::PaddleGrow  => self.paddle.powerup_grow(),
-                    PowerupKind::PaddleGun   => self.paddle.powerup_gun(),
-                    PowerupKind::BallsSafe   => self.paddle.powerup_balls_safe(),
+                    PowerupKind::PaddleCarry => self.paddle.powerup_carry(&self.rules),
+                    PowerupKind::PaddleGrow  => self.paddle.powerup_grow(&self.rules),
+                    PowerupKind::PaddleGun   => self.paddle.powerup_gun(&self.rules),
+                    PowerupKind::BallsSafe   => self.paddle.powerup_balls_safe(&self.rules),
+                    PowerupKind::PaddleMagnet => self.paddle.powerup_magnet(&self.rules),
+                    PowerupKind::BallsFire    => self.paddle.powerup_fireball(&self.rules),
+                    PowerupKind::SlowTime     => self.paddle.powerup_slow(&self.rules),
+                    // Nothing to add to if lives are infinite, or already at the most there can be
+                    PowerupKind::ExtraLife => if let Some(lives) = self.lives.as_mut().filter(|l| **l < self.rules.max_lives) {
+                        *lives += 1;
+                        self.events.push(WorldEvent::LifeGained { lives: *lives });
+                    },
                     PowerupKind::BallsFive   => angled_balls.push(5),
                     PowerupKind::BallsTrail  => trail = true,
-                    _ => {},
+                    PowerupKind::Zap         => zap = true,
+                    PowerupKind::PaddleShrink     => self.paddle.powerup_shrink(&self.rules),
+                    PowerupKind::BallsFast        => self.paddle.powerup_balls_fast(&self.rules),
+                    PowerupKind::ControlsReversed => self.paddle.powerup_reverse(&self.rules),
                 };
             }
             if hit_state != PowerupHitState::None {
@@ -253,6 +512,13 @@ impl World {
         if trail {
             self.trail_balls();
         }
+        if zap {
+            self.zap();
+        }
+        for (_, _, t) in &mut self.bolts {
+            *t -= delta;
+        }
+        self.bolts.retain(|(_, _, t)| *t > 0.0);
 
         // All balls are gone, there are no powerups left, the game is lost! Lose a life and either dispense another ball or game-over
         // Only if we haven't won of course!!
@@ -268,6 +534,7 @@ impl World {
                 }
                 if self.lives.is_some_and(|l| l != 0) {
                     self.lives = self.lives.map(|l| l - 1);
+                    self.events.push(WorldEvent::LifeLost { lives_left: self.lives.unwrap_or(0) });
                     self.paddle.carry_new();
                 }
             }
@@ -287,15 +554,20 @@ impl World {
             self.bullets.remove(*i);
         }
 
-        match (gameover, self.ball_stuck_timer >= 30.0) {
-            (true, _) => WorldUpdateReturn::GameOver,
-            (_, true) => {self.ball_stuck_timer = 0.0; WorldUpdateReturn::BallStuck},
-            _ => WorldUpdateReturn::None
+        if gameover {
+            self.events.push(WorldEvent::GameOver);
+        } else if self.ball_stuck_timer >= self.rules.ball_stuck_time {
+            self.ball_stuck_timer = 0.0;
+            self.events.push(WorldEvent::BallStuck);
         }
+
+        std::mem::take(&mut self.events)
     }
 
-    pub fn draw(&self, texture: &Texture2D) {
-        clear_background(BG_COL);
+    // 'alpha' is how far between the last tick and the next one we are, so movement can be drawn smoothly
+    #[cfg(feature = "graphics")]
+    pub fn draw(&self, texture: &Texture2D, alpha: f32) {
+        clear_background(self.level.meta().background_color().unwrap_or(BG_COL));
 
         let view_size = Level::view_size();
         // Safety net
@@ -308,16 +580,34 @@ impl World {
         }
         // Actual stuff
         for p in &self.powerups {
-            p.draw(texture);
+            p.draw(texture, alpha);
         }
         self.level.draw(texture);
+        // Explosives about to go off flash brighter and brighter
+        for (index, t) in &self.explosions {
+            let rect = self.level.tile_rect(*index);
+            let amount = 1.0 - (t / self.rules.explosion_delay).clamp(0.0, 1.0);
+            draw_rectangle(rect.x - 1.0, rect.y - 1.0, rect.w + 2.0, rect.h + 2.0, Color::new(1.0, 0.9, 0.4, 0.3 + amount * 0.7));
+        }
+        // Zap bolts zigzag a little, always the same way for the same bolt so they don't flicker
+        for (from, to, t) in &self.bolts {
+            let col = Color::new(0.8, 0.9, 1.0, t / ZAP_BOLT_TIME);
+            let side = (*to - *from).normalize_or_zero().perp();
+            let mut prev = *from;
+            for i in 1..=6 {
+                let wiggle = if i == 6 { 0.0 } else { ((i + to.x as usize) % 3) as f32 * 2.0 - 2.0 };
+                let point = from.lerp(*to, i as f32 / 6.0) + side * wiggle;
+                draw_line(prev.x, prev.y, point.x, point.y, 1.0, col);
+                prev = point;
+            }
+        }
         for b in &self.balls {
-            b.draw(texture);
+            b.draw(texture, alpha, self.paddle.fireball_display());
         }
         for b in &self.bullets {
-            b.draw(texture);
+            b.draw(texture, alpha);
         }
-        self.paddle.draw(texture);
+        self.paddle.draw(texture, alpha);
 
         // HUD
         let mut x = 1.0;
@@ -335,9 +625,480 @@ impl World {
             });
             x += BALL_SIZE + 1.0;
         }
+        if self.paddle.magnet_display() {
+            draw_texture_ex(texture, x, view_size.y - BALL_SIZE - 1.0, WHITE, DrawTextureParams {
+                source: Some(MAGNET_ICON_TEXTURE),
+                ..Default::default()
+            });
+        }
+
+        render_text(&format!("SCORE: {}", self.score), vec2(0.0, 0.0), WHITE, TextAlign::Left, texture);
+        render_text(self.level.name(), vec2(Level::view_size().x, 0.0), WHITE, TextAlign::Right, texture);
+        // The level's hint, until the first ball goes
+        if !self.launched && !self.level.meta().hint.is_empty() {
+            let hint = &self.level.meta().hint;
+            let y = Paddle::y() - 30.0;
+            let w = hint.len() as f32 * 6.0 + 3.0;
+            draw_rectangle((view_size.x - w) / 2.0, y - 2.0, w, 10.0, Color::from_rgba(0, 0, 0, 160));
+            render_text(hint, vec2(view_size.x / 2.0, y), WHITE, TextAlign::Center, texture);
+        }
+        render_text("JUMBLEDFOX.GITHUB.IO", Level::view_size() - vec2(0.0, 7.0), Color::from_rgba(255, 255, 255, 128), TextAlign::Right, texture);
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::level_pack::LevelPack;
+    use ball::BALL_SIZE;
+    use level::{MotionAxis, RowMotion, LEVEL_WIDTH};
+
+    // The first level of the pack that comes with the game, which has a bit of everything
+    fn test_world(seed: u64) -> World {
+        let level_pack = LevelPack::load_from_file(include_bytes!("../../../ALBUMS I LIKE.brk").to_vec()).unwrap();
+        World::new(level_pack.levels()[0].clone(), 0, None, None, Lives::Infinite, None, seed, GameRules::default())
+    }
+
+    // Follows the lowest falling ball and shoots whenever it can, so plenty happens
+    fn bot_input(world: &World, tick: usize) -> InputFrame {
+        let paddle_center = world.paddle().x() + world.paddle().width() / 2.0;
+        let target = world.balls()
+            .iter()
+            .filter(|b| b.vel().y > 0.0)
+            .max_by(|a, b| a.pos().y.total_cmp(&b.pos().y))
+            .map(|b| b.pos().x)
+            .unwrap_or(paddle_center);
+
+        InputFrame {
+            move_axis: ((target - paddle_center) / 4.0).clamp(-1.0, 1.0),
+            fire: true,
+            carry_released: tick.is_multiple_of(TICK_RATE as usize),
+            ..Default::default()
+        }
+    }
+
+    // The same every time, whatever's going on in the world
+    fn scripted_input(tick: usize) -> InputFrame {
+        InputFrame {
+            move_axis: (tick as f32 / 90.0).sin(),
+            fire: tick % 400 < 200,
+            carry_released: tick.is_multiple_of(TICK_RATE as usize),
+            ..Default::default()
+        }
+    }
+
+    fn run_scripted(seed: u64, ticks: usize) -> (Vec<WorldEvent>, usize) {
+        let mut world = test_world(seed);
+        let mut events = Vec::new();
+        for tick in 0..ticks {
+            events.extend(world.update(scripted_input(tick)));
+        }
+        (events, world.score())
+    }
+
+    #[test]
+    fn same_seed_same_game() {
+        let (events, score) = run_scripted(7, 8000);
+        assert!(events.iter().any(|e| matches!(e, WorldEvent::TileBroken { .. })));
+        assert!(events.iter().any(|e| matches!(e, WorldEvent::PowerupSpawned { .. })));
+        assert_eq!(run_scripted(7, 8000), (events, score));
+    }
+
+    #[test]
+    fn different_seed_different_game() {
+        let games: Vec<_> = (0..4).map(|seed| run_scripted(seed, 8000)).collect();
+        for (i, a) in games.iter().enumerate() {
+            for b in &games[i + 1..] {
+                assert_ne!(a, b);
+            }
+        }
+    }
+
+    fn run(world: &mut World, ticks: std::ops::Range<usize>) -> Vec<WorldEvent> {
+        let mut events = Vec::new();
+        for tick in ticks {
+            let tick_events = world.update(bot_input(world, tick));
+            if tick_events.contains(&WorldEvent::BallStuck) {
+                world.give_free_ball();
+            }
+            events.extend(tick_events);
+        }
+        events
+    }
+
+    fn snapshot(world: &World) -> Vec<u8> {
+        let mut w = ByteWriter::new();
+        world.write_snapshot(&mut w);
+        w.into_bytes()
+    }
+
+    #[test]
+    fn snapshot_carries_on_the_same() {
+        let mut world = test_world(5);
+        run(&mut world, 0..3000);
+        assert!(!world.level_complete() && !world.balls().is_empty());
+        let bytes = snapshot(&world);
+
+        let mut r = ByteReader::new(&bytes);
+        let mut restored = World::read_snapshot(&mut r).unwrap();
+        assert!(r.finished());
+        assert_eq!(snapshot(&restored), bytes);
+
+        let events = run(&mut world, 3000..6000);
+        assert!(!events.is_empty());
+        assert_eq!(run(&mut restored, 3000..6000), events);
+        assert_eq!(restored.score(), world.score());
+        assert_eq!(snapshot(&restored), snapshot(&world));
+    }
+
+    #[test]
+    fn snapshot_truncated() {
+        let mut world = test_world(5);
+        run(&mut world, 0..3000);
+        let bytes = snapshot(&world);
+        for len in 0..bytes.len() {
+            assert!(World::read_snapshot(&mut ByteReader::new(&bytes[..len])).is_none(), "read {len} of {} bytes", bytes.len());
+        }
+    }
+
+    // A world with nothing in it but 'tiles'
+    fn world_with(tiles: &[(usize, usize, Tile)], rules: GameRules) -> World {
+        let mut level = Level::new();
+        for (x, y, tile) in tiles {
+            level.tiles_mut()[y * LEVEL_WIDTH + x] = *tile;
+        }
+        World::new(level, 0, None, None, Lives::Infinite, None, 1, rules)
+    }
+
+    fn ball_at(pos: Vec2, vel: Vec2) -> Ball {
+        let mut ball = Ball::new(pos, 0.0, 0.0);
+        ball.set_vel(vel);
+        ball
+    }
+
+    // Fast enough that a ball goes further than a tile's height every tick
+    fn fast_rules() -> GameRules {
+        GameRules { ball_speed: 2400.0, ..Default::default() }
+    }
+
+    #[test]
+    fn fast_ball_breaks_tile() {
+        let mut world = world_with(&[(5, 10, Tile::Red)], fast_rules());
+        let index = 10 * LEVEL_WIDTH + 5;
+        let rect = world.level.tile_rect(index);
+        world.balls.push(ball_at(vec2(rect.x + 3.0, rect.bottom() + 10.0), vec2(0.0, -1.0)));
+
+        let events = world.update(InputFrame::default());
+        assert!(events.iter().any(|e| matches!(e, WorldEvent::TileBroken { index: i, .. } if *i == index)));
+        assert_eq!(world.level.tiles()[index], Tile::Air);
+        assert!(world.balls[0].vel().y > 0.0);
+        assert!(world.balls[0].pos().y >= rect.bottom());
+    }
+
+    #[test]
+    fn fast_ball_bounces_off_solid_tile() {
+        let mut world = world_with(&[(5, 10, Tile::Metal)], fast_rules());
+        let index = 10 * LEVEL_WIDTH + 5;
+        let rect = world.level.tile_rect(index);
+        world.balls.push(ball_at(vec2(rect.x + 3.0, rect.bottom() + 10.0), vec2(0.0, -1.0)));
+
+        for _ in 0..3 {
+            let events = world.update(InputFrame::default());
+            assert!(!events.iter().any(|e| matches!(e, WorldEvent::TileBroken { .. })));
+        }
+        assert_eq!(world.level.tiles()[index], Tile::Metal);
+        assert!(world.balls[0].vel().y > 0.0);
+        assert!(world.balls[0].pos().y >= rect.bottom());
+    }
+
+    #[test]
+    fn fast_ball_bounces_off_paddle() {
+        let mut world = world_with(&[], fast_rules());
+        let paddle_center = world.paddle.x() + world.paddle.width() / 2.0;
+        world.balls.push(ball_at(vec2(paddle_center - 2.0, Paddle::y() - 10.0), vec2(0.0, 1.0)));
+
+        let events = world.update(InputFrame::default());
+        assert!(!events.iter().any(|e| matches!(e, WorldEvent::BallLost { .. })));
+        assert_eq!(world.balls.len(), 1);
+        assert!(world.balls[0].vel().y < 0.0);
+        assert!(world.balls[0].pos().y < Paddle::y());
+    }
+
+    #[test]
+    fn portal_sends_ball_out_of_partner() {
+        let mut world = world_with(&[(3, 10, Tile::PortalA), (10, 4, Tile::PortalB)], GameRules::default());
+        let (from, to) = (world.level.tile_rect(10 * LEVEL_WIDTH + 3), world.level.tile_rect(4 * LEVEL_WIDTH + 10));
+        let vel = vec2(0.6, -0.8);
+        world.balls.push(ball_at(vec2(from.x + 3.0, from.bottom() + 0.1), vel));
+
+        let events = world.update(InputFrame::default());
+        assert!(!events.iter().any(|e| matches!(e, WorldEvent::TileHit { .. } | WorldEvent::TileBroken { .. })));
+        let ball = &world.balls[0];
+        assert_eq!(ball.vel(), vel);
+        // Out of the top of the partner, the same distance along it, having carried on for the rest of the tick
+        assert!((ball.pos().x - (to.x + 3.0)).abs() < 1.0);
+        assert!(ball.pos().y < to.y - BALL_SIZE && ball.pos().y > to.y - BALL_SIZE - 1.0);
+        assert_eq!(world.level.tiles()[10 * LEVEL_WIDTH + 3], Tile::PortalA);
+        assert_eq!(world.level.tiles()[4 * LEVEL_WIDTH + 10], Tile::PortalB);
+    }
+
+    #[test]
+    fn unpaired_portal_bounces() {
+        let mut world = world_with(&[(3, 10, Tile::PortalA)], GameRules::default());
+        let from = world.level.tile_rect(10 * LEVEL_WIDTH + 3);
+        world.balls.push(ball_at(vec2(from.x + 3.0, from.bottom() + 0.1), vec2(0.0, -1.0)));
+
+        let events = world.update(InputFrame::default());
+        assert!(!events.iter().any(|e| matches!(e, WorldEvent::TileHit { .. } | WorldEvent::TileBroken { .. })));
+        assert!(world.balls[0].vel().y > 0.0);
+        assert!(world.balls[0].pos().y >= from.bottom());
+        assert_eq!(world.level.tiles()[10 * LEVEL_WIDTH + 3], Tile::PortalA);
+    }
+
+    #[test]
+    fn explosives_chain() {
+        // The first explosive is in the corner, so its blast goes off the edge of the level
+        let mut world = world_with(&[
+            (0, 0, Tile::Explosive), (1, 0, Tile::Explosive), (2, 0, Tile::Red), (3, 0, Tile::Red),
+            (0, 1, Tile::Metal), (1, 1, Tile::Red), (2, 1, Tile::Stone),
+        ], GameRules::default());
+        world.break_tile(0, HitSource::Ball);
+        let mut events = world.events.clone();
+        for _ in 0..100 {
+            if world.explosions.is_empty() {
+                break;
+            }
+            events.extend(world.update(InputFrame::default()));
+        }
+        assert!(world.explosions.is_empty());
+
+        let count = |f: fn(&WorldEvent) -> bool| events.iter().filter(|e| f(e)).count();
+        assert_eq!(count(|e| matches!(e, WorldEvent::TileExploded { .. })), 2);
+        assert_eq!(count(|e| matches!(e, WorldEvent::TileBroken { .. })), 4);
+        assert_eq!(count(|e| matches!(e, WorldEvent::TileHit { .. })), 1);
+        assert_eq!(&world.level.tiles()[..4], &[Tile::Air, Tile::Air, Tile::Air, Tile::Red]);
+        assert_eq!(&world.level.tiles()[LEVEL_WIDTH..LEVEL_WIDTH + 3], &[Tile::Metal, Tile::Air, Tile::StoneCracked]);
+    }
+
+    #[test]
+    fn bullets_pass_through_what_they_cant_break() {
+        let mut rules = GameRules::default();
+        rules.materials[Tile::Green as usize].bullet_breakable = false;
+        let mut world = world_with(&[(5, 8, Tile::Red), (5, 12, Tile::Green)], rules);
+        let (red, green) = (8 * LEVEL_WIDTH + 5, 12 * LEVEL_WIDTH + 5);
+        let rect = world.level.tile_rect(green);
+        world.bullets.push(Bullet::new(vec2(rect.x + 5.0, rect.bottom() + 20.0)));
+
+        let mut events = Vec::new();
+        for _ in 0..120 {
+            events.extend(world.update(InputFrame::default()));
+        }
+        assert!(world.bullets.is_empty());
+        assert_eq!(world.level.tiles()[green], Tile::Green);
+        assert_eq!(world.level.tiles()[red], Tile::Air);
+        let broken: Vec<_> = events.iter().filter_map(|e| match e {
+            WorldEvent::TileBroken { index, .. } => Some(*index),
+            _ => None,
+        }).collect();
+        assert_eq!(broken, vec![red]);
+    }
+
+    #[test]
+    fn hit_points_come_from_materials() {
+        let mut rules = GameRules::default();
+        rules.materials[Tile::Red as usize].hit_points = 3;
+        let (a, b) = (2 * LEVEL_WIDTH + 2, 2 * LEVEL_WIDTH + 4);
+        let mut level = Level::new();
+        level.tiles_mut()[a] = Tile::Red;
+        level.tiles_mut()[b] = Tile::Red;
+        // Set by the level, so the material doesn't change it
+        level.hit_points_mut()[b] = 2;
+        let mut world = World::new(level, 0, None, None, Lives::Infinite, None, 1, rules);
+        assert_eq!(world.level.hit_points()[a], 3);
+        assert_eq!(world.level.hit_points()[b], 2);
+
+        for _ in 0..2 {
+            world.events.clear();
+            world.break_tile(a, HitSource::Ball);
+            assert!(matches!(world.events[..], [WorldEvent::TileHit { .. }]));
+            assert_eq!(world.level.tiles()[a], Tile::Red);
+        }
+        world.events.clear();
+        world.break_tile(a, HitSource::Ball);
+        assert!(matches!(world.events[0], WorldEvent::TileBroken { .. }));
+        assert_eq!(world.level.tiles()[a], Tile::Air);
+    }
+
+    #[test]
+    fn moving_row_pushes_ball() {
+        let row: Vec<_> = (0..LEVEL_WIDTH).map(|x| (x, 10, Tile::Metal)).collect();
+        let mut world = world_with(&row, GameRules::default());
+        world.level.motions_mut()[10] = RowMotion { axis: MotionAxis::Vertical, distance: 20, period: 1.0, phase: 0.0 };
+        let index = 10 * LEVEL_WIDTH + 5;
+        let rect = world.level.tile_rect(index);
+        // Going up slowly, while the row comes down much faster
+        world.balls.push(ball_at(vec2(rect.x + 3.0, rect.bottom() + 2.0), vec2(0.0, -0.1)));
+
+        for _ in 0..30 {
+            world.update(InputFrame::default());
+            assert!(world.balls[0].pos().y >= world.level.tile_rect(index).bottom() - 0.01);
+        }
+        assert!(world.level.row_offset(10).y > 2.0);
+        assert!(world.balls[0].vel().y > 0.0);
+    }
+
+    #[test]
+    fn zap_hits_closest_breakable_tiles() {
+        let rules = GameRules { zap_tiles: 2, ..Default::default() };
+        // The metal's closest to the paddle, but zaps can't break it
+        let mut world = world_with(&[
+            (7, 21, Tile::Metal), (8, 21, Tile::Metal),
+            (7, 19, Tile::Red), (8, 19, Tile::Red),
+            (0, 0, Tile::Red), (15, 0, Tile::Red),
+        ], rules);
+        world.zap();
+
+        let broken: Vec<_> = world.events.iter().filter_map(|e| match e {
+            WorldEvent::TileBroken { index, .. } => Some(*index),
+            _ => None,
+        }).collect();
+        assert_eq!(broken.len(), 2);
+        assert!(broken.contains(&(19 * LEVEL_WIDTH + 7)) && broken.contains(&(19 * LEVEL_WIDTH + 8)));
+        assert!(matches!(world.events.last(), Some(WorldEvent::Zapped { tiles: 2, score: 20, .. })));
+        assert_eq!(world.level.tiles()[21 * LEVEL_WIDTH + 7], Tile::Metal);
+        assert_eq!(world.level.tiles()[0], Tile::Red);
+        assert_eq!(world.level.tiles()[15], Tile::Red);
+    }
+
+    // Drops a powerup straight onto the paddle, running the world until it's caught
+    fn catch(world: &mut World, kind: PowerupKind) -> Vec<WorldEvent> {
+        let pos = vec2(world.paddle.x() + world.paddle.width() / 2.0 - 5.0, Paddle::y() - 10.0);
+        world.powerups.push(Powerup::with_kind(pos, kind, &mut world.rng));
+        let mut events = Vec::new();
+        for _ in 0..TICK_RATE as usize {
+            events.extend(world.update(InputFrame::default()));
+            if events.iter().any(|e| matches!(e, WorldEvent::PowerupCollected { .. })) {
+                return events;
+            }
+        }
+        panic!("{kind:?} wasn't caught");
+    }
+
+    #[test]
+    fn shrink_powerup() {
+        let mut world = world_with(&[], GameRules::default());
+        let events = catch(&mut world, PowerupKind::PaddleShrink);
+        // No points for catching a bad one
+        assert!(events.iter().any(|e| matches!(e, WorldEvent::PowerupCollected { kind: PowerupKind::PaddleShrink, score: 0, .. })));
+        for _ in 0..TICK_RATE as usize {
+            world.update(InputFrame::default());
+        }
+        assert_eq!(world.paddle.width(), world.rules.paddle_width_short);
+    }
+
+    #[test]
+    fn reversed_controls() {
+        let mut world = world_with(&[], GameRules::default());
+        catch(&mut world, PowerupKind::ControlsReversed);
+        let x = world.paddle.x();
+        for _ in 0..10 {
+            world.update(InputFrame { move_axis: 1.0, ..Default::default() });
+        }
+        assert!(world.paddle.x() < x);
+    }
+
+    #[test]
+    fn fast_balls() {
+        let distance_moved = |fast: bool| {
+            let mut world = world_with(&[], GameRules::default());
+            if fast {
+                catch(&mut world, PowerupKind::BallsFast);
+            }
+            world.balls.push(ball_at(vec2(50.0, 100.0), vec2(0.0, -1.0)));
+            world.update(InputFrame::default());
+            100.0 - world.balls[0].pos().y
+        };
+        let ratio = distance_moved(true) / distance_moved(false);
+        assert!((ratio - GameRules::default().fast_ball_speed).abs() < 0.001);
+    }
+
+    #[test]
+    fn magnet_catches_every_ball() {
+        let mut world = world_with(&[], GameRules::default());
+        // Out of the way of the balls coming down
+        world.update(InputFrame { carry_released: true, ..Default::default() });
+        world.balls.clear();
+        catch(&mut world, PowerupKind::PaddleMagnet);
+
+        let x = world.paddle.x();
+        for offset in [1.0, 8.0, 15.0] {
+            world.balls.push(ball_at(vec2(x + offset, Paddle::y() - 10.0 - offset), vec2(0.0, 1.0)));
+        }
+        let mut caught = 0;
+        for _ in 0..TICK_RATE as usize {
+            caught += world.update(InputFrame::default()).iter().filter(|e| matches!(e, WorldEvent::BallCaught { .. })).count();
+        }
+        assert_eq!(caught, 3);
+        assert!(world.balls.is_empty() && world.paddle.carrying());
+
+        world.update(InputFrame { carry_released: true, ..Default::default() });
+        assert_eq!(world.balls.len(), 3);
+        assert!(world.balls.iter().all(|b| b.vel().y < 0.0));
+    }
+
+    #[test]
+    fn fireball_pierces() {
+        let mut rules = GameRules::default();
+        rules.materials[Tile::Red as usize].hit_points = 3;
+        let mut world = world_with(&[(5, 10, Tile::Red), (5, 9, Tile::Stone), (5, 8, Tile::Red), (5, 6, Tile::Metal)], rules);
+        catch(&mut world, PowerupKind::BallsFire);
+        let rect = world.level.tile_rect(10 * LEVEL_WIDTH + 5);
+        world.balls.push(ball_at(vec2(rect.x + 3.0, rect.bottom() + 2.0), vec2(0.0, -1.0)));
+
+        let mut events = Vec::new();
+        for _ in 0..TICK_RATE as usize {
+            events.extend(world.update(InputFrame::default()));
+            if world.balls[0].vel().y > 0.0 {
+                break;
+            }
+        }
+        // Everything breaks in one go on the way through, and only the metal stops it
+        assert!(world.balls[0].vel().y > 0.0);
+        assert!(!events.iter().any(|e| matches!(e, WorldEvent::TileHit { .. })));
+        assert_eq!(events.iter().filter(|e| matches!(e, WorldEvent::TileBroken { .. })).count(), 3);
+        for y in 8..=10 {
+            assert_eq!(world.level.tiles()[y * LEVEL_WIDTH + 5], Tile::Air);
+        }
+        assert_eq!(world.level.tiles()[6 * LEVEL_WIDTH + 5], Tile::Metal);
+    }
+
+    #[test]
+    fn slow_time() {
+        // How far a ball and a powerup move in a tick, both well away from the paddle
+        let moved = |slow: bool| {
+            let mut world = world_with(&[], GameRules::default());
+            if slow {
+                catch(&mut world, PowerupKind::SlowTime);
+            }
+            world.balls.push(ball_at(vec2(50.0, 100.0), vec2(0.0, -1.0)));
+            world.powerups.push(Powerup::with_kind(vec2(100.0, 50.0), PowerupKind::PaddleGun, &mut Rng::new(1)));
+            world.update(InputFrame::default());
+            (100.0 - world.balls[0].pos().y, world.powerups[0].pos().y - 49.0)
+        };
+        let ((slow_ball, slow_powerup), (ball, powerup)) = (moved(true), moved(false));
+        let slow_speed = GameRules::default().slow_speed;
+        assert!((slow_ball / ball - slow_speed).abs() < 0.001);
+        assert!((slow_powerup / powerup - slow_speed).abs() < 0.001);
+    }
 
-        render_text(&format!("SCORE: {}", self.score), vec2(0.0, 0.0), WHITE, TextAlign::Left, &texture);
-        render_text(self.level.name(), vec2(Level::view_size().x, 0.0), WHITE, TextAlign::Right, &texture);
-        render_text(&format!("JUMBLEDFOX.GITHUB.IO").to_uppercase(), Level::view_size() - vec2(0.0, 7.0), Color::from_rgba(255, 255, 255, 128), TextAlign::Right, &texture);
+    #[test]
+    fn extra_life_up_to_max() {
+        let rules = GameRules::default();
+        let mut world = World::new(Level::new(), 0, None, None, Lives::Some(rules.max_lives - 1), None, 1, rules.clone());
+        let gained = |events: Vec<WorldEvent>| events.iter().filter(|e| matches!(e, WorldEvent::LifeGained { .. })).count();
+
+        assert_eq!(gained(catch(&mut world, PowerupKind::ExtraLife)), 1);
+        assert_eq!(world.lives, Some(rules.max_lives));
+        assert_eq!(gained(catch(&mut world, PowerupKind::ExtraLife)), 0);
+        assert_eq!(world.lives, Some(rules.max_lives));
     }
-}
\ No newline at end of file
+}
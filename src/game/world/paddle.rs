@@ -1,16 +1,10 @@
-use std::f32::NEG_INFINITY;
+use macroquad::math::{vec2, Rect, Vec2};
+#[cfg(feature = "graphics")]
+use macroquad::{color::{Color, WHITE}, texture::{draw_texture_ex, DrawTextureParams, Texture2D}};
 
-use macroquad::{color::WHITE, input::{is_key_down, is_key_released, KeyCode}, math::{vec2, Rect, Vec2}, texture::{draw_texture_ex, DrawTextureParams, Texture2D}};
+use crate::game::bytes::{ByteReader, ByteWriter};
 
-use super::{ball::{Ball, BALL_SIZE}, bullet::Bullet, level::Level};
-
-const KEY_CARRY: KeyCode = KeyCode::Space;
-
-const PADDLE_SPEED: f32 = 100.0;
-
-const WIDTH_DEFAULT: f32 = 20.0;
-const WIDTH_LONG:    f32 = 40.0;
-const GROWTH_SPEED:  f32 = 40.0;
+use super::{ball::{Ball, BALL_SIZE}, bullet::Bullet, input::InputFrame, level::Level, rules::GameRules};
 
 pub const PADDLE_LEFT_TEXTURE: Rect = Rect { x: 122.0, y: 8.0, w: 1.0, h: 4.0 };
 pub const PADDLE_CENTER_TEXTURE: Rect = Rect { x: 123.0, y: 8.0, w: 1.0, h: 4.0 };
@@ -18,6 +12,7 @@ pub const PADDLE_RIGHT_TEXTURE: Rect = Rect { x: 124.0, y: 8.0, w: 1.0, h: 4.0 }
 
 pub struct Paddle {
     x: f32,
+    prev_x: f32,
     vel: f32,
     width: f32,
     target_width: f32,
@@ -25,32 +20,91 @@ pub struct Paddle {
     carries: usize,
     carry: Option<Ball>,
     carry_x: f32,
+    // Balls the magnet's caught, and how far along the paddle they are
+    stuck: Vec<(Ball, f32)>,
 
     long:       Option<f32>,
     gun:        Option<f32>,
     balls_safe: Option<f32>,
+    short:      Option<f32>,
+    balls_fast: Option<f32>,
+    reversed:   Option<f32>,
+    magnet:     Option<f32>,
+    fireball:   Option<f32>,
+    slow:       Option<f32>,
 
     shot_timer: f32,
 }
 
 impl Paddle {
-    pub fn new(x: Option<f32>, carries: Option<usize>) -> Self {
+    pub fn new(x: Option<f32>, carries: Option<usize>, rules: &GameRules) -> Self {
+        let x = x.unwrap_or((Level::view_size().x - rules.paddle_width) / 2.0);
         Self {
-            x: x.unwrap_or((Level::view_size().x - WIDTH_DEFAULT) / 2.0),
+            x,
+            prev_x: x,
             vel: 0.0,
-            width: WIDTH_DEFAULT,
-            target_width: WIDTH_DEFAULT,
+            width: rules.paddle_width,
+            target_width: rules.paddle_width,
 
             carries: carries.unwrap_or(0),
             carry: Some(Ball::new(vec2(0.0, 0.0), f32::to_radians(90.0), 1.0)),
-            carry_x: (WIDTH_DEFAULT - BALL_SIZE) / 2.0,
+            carry_x: (rules.paddle_width - BALL_SIZE) / 2.0,
+            stuck: Vec::new(),
 
             long:       None,
             gun:        None,
             balls_safe: None,
+            short:      None,
+            balls_fast: None,
+            reversed:   None,
+            magnet:     None,
+            fireball:   None,
+            slow:       None,
+
+            shot_timer: f32::NEG_INFINITY,
+        }
+    }
 
-            shot_timer: NEG_INFINITY,
+    pub fn write_snapshot(&self, w: &mut ByteWriter) {
+        w.f32(self.x);
+        w.f32(self.prev_x);
+        w.f32(self.vel);
+        w.f32(self.width);
+        w.f32(self.target_width);
+        w.usize(self.carries);
+        w.option(self.carry.as_ref(), |w, b| b.write_snapshot(w));
+        w.f32(self.carry_x);
+        w.list(&self.stuck, |w, (b, x)| {
+            b.write_snapshot(w);
+            w.f32(*x);
+        });
+        for timer in [self.long, self.gun, self.balls_safe, self.short, self.balls_fast, self.reversed, self.magnet, self.fireball, self.slow] {
+            w.option(timer, ByteWriter::f32);
         }
+        w.f32(self.shot_timer);
+    }
+    pub fn read_snapshot(r: &mut ByteReader) -> Option<Self> {
+        Some(Self {
+            x: r.f32()?,
+            prev_x: r.f32()?,
+            vel: r.f32()?,
+            width: r.f32()?,
+            target_width: r.f32()?,
+            carries: r.usize()?,
+            carry: r.option(Ball::read_snapshot)?,
+            carry_x: r.f32()?,
+            stuck: r.list(|r| Some((Ball::read_snapshot(r)?, r.f32()?)))?,
+            long:       r.option(ByteReader::f32)?,
+            gun:        r.option(ByteReader::f32)?,
+            balls_safe: r.option(ByteReader::f32)?,
+            short:      r.option(ByteReader::f32)?,
+            balls_fast: r.option(ByteReader::f32)?,
+            reversed:   r.option(ByteReader::f32)?,
+            magnet:     r.option(ByteReader::f32)?,
+            fireball:   r.option(ByteReader::f32)?,
+            slow:       r.option(ByteReader::f32)?,
+            shot_timer: r.f32()?,
+        })
     }
 
     // How far 'x' is from the center to the edge of the paddle, mapped from -1.0 (left edge) to 0.0 (center) to 1.0 (right edge)  
@@ -66,6 +120,9 @@ impl Paddle {
     pub fn x(&self) -> f32 {
         self.x
     }
+    pub fn width(&self) -> f32 {
+        self.width
+    }
     pub fn y() -> f32 {
         Level::view_size().y - 12.0
     }
@@ -74,32 +131,58 @@ impl Paddle {
     }
 
     pub fn carrying(&self) -> bool {
-        self.carry.is_some()
+        self.carry.is_some() || !self.stuck.is_empty()
     }
-    pub fn can_carry(&self) -> bool {
-        self.carries != 0 && self.carry.is_none() && is_key_down(KEY_CARRY)
+    pub fn can_carry(&self, input: &InputFrame) -> bool {
+        self.carries != 0 && self.carry.is_none() && input.carry_held
     }
     pub fn carry(&mut self, ball: Ball) {
         self.carries = self.carries.saturating_sub(1);
         self.carry_x = ball.pos().x - self.x;
         self.carry = Some(ball);
     }
+    // Holds onto a ball that's just bounced off the paddle, going the way it bounced, until the carry key's let go
+    pub fn stick(&mut self, ball: Ball) {
+        let x = (ball.pos().x - self.x).clamp(0.0, self.width - BALL_SIZE);
+        self.stuck.push((ball, x));
+    }
     pub fn carry_new(&mut self) {
         self.carry = Some(Ball::new(vec2(0.0, 0.0), 0.0, 1.0));
         self.carry_x = (self.width - BALL_SIZE) / 2.0;
     }
 
-    pub fn powerup_gun(&mut self) {
-        self.gun = Some(7.0);
+    pub fn powerup_gun(&mut self, rules: &GameRules) {
+        self.gun = Some(rules.gun_duration);
+    }
+    // Growing and shrinking cancel each other out
+    pub fn powerup_grow(&mut self, rules: &GameRules) {
+        self.long = Some(rules.grow_duration);
+        self.short = None;
+    }
+    pub fn powerup_shrink(&mut self, rules: &GameRules) {
+        self.short = Some(rules.shrink_duration);
+        self.long = None;
+    }
+    pub fn powerup_balls_fast(&mut self, rules: &GameRules) {
+        self.balls_fast = Some(rules.fast_duration);
+    }
+    pub fn powerup_magnet(&mut self, rules: &GameRules) {
+        self.magnet = Some(rules.magnet_duration);
+    }
+    pub fn powerup_fireball(&mut self, rules: &GameRules) {
+        self.fireball = Some(rules.fireball_duration);
     }
-    pub fn powerup_grow(&mut self) {
-        self.long = Some(15.0);
+    pub fn powerup_slow(&mut self, rules: &GameRules) {
+        self.slow = Some(rules.slow_duration);
     }
-    pub fn powerup_balls_safe(&mut self) {
-        self.balls_safe = Some(7.0);
+    pub fn powerup_reverse(&mut self, rules: &GameRules) {
+        self.reversed = Some(rules.reverse_duration);
     }
-    pub fn powerup_carry(&mut self) {
-        if self.carries <3 { // awwww :3
+    pub fn powerup_balls_safe(&mut self, rules: &GameRules) {
+        self.balls_safe = Some(rules.balls_safe_duration);
+    }
+    pub fn powerup_carry(&mut self, rules: &GameRules) {
+        if self.carries < rules.max_carries { // awwww :3
             self.carries += 1
         }
     }
@@ -111,6 +194,30 @@ impl Paddle {
     pub fn balls_safe(&self) -> bool {
         self.balls_safe.is_some()
     }
+    pub fn magnet(&self) -> bool {
+        self.magnet.is_some()
+    }
+    // The magnet's HUD icon flashes when it's about to run out
+    pub fn magnet_display(&self) -> bool {
+        self.magnet.is_some_and(|t| t % 0.25 <= 0.125 || t > 1.5)
+    }
+    pub fn fireball(&self) -> bool {
+        self.fireball.is_some()
+    }
+    // Balls flicker back to normal when it's about to run out
+    pub fn fireball_display(&self) -> bool {
+        self.fireball.is_some_and(|t| t % 0.25 <= 0.125 || t > 1.5)
+    }
+    // How much balls and powerups are slowed down by, 1.0 when they aren't
+    pub fn time_scale(&self, rules: &GameRules) -> f32 {
+        if self.slow.is_some() { rules.slow_speed } else { 1.0 }
+    }
+    pub fn balls_fast(&self) -> bool {
+        self.balls_fast.is_some()
+    }
+    pub fn reversed(&self) -> bool {
+        self.reversed.is_some()
+    }
     pub fn balls_safe_display(&self) -> bool {
         self.balls_safe.is_some_and(|t| t % 0.25 <= 0.125 || t > 1.5)
     }
@@ -131,10 +238,11 @@ impl Paddle {
         )
     }
 
-    pub fn update(&mut self, delta: f32, bullets: &mut Vec<Bullet>) -> Option<Ball> {
+    pub fn update(&mut self, delta: f32, input: &InputFrame, bullets: &mut Vec<Bullet>, rules: &GameRules) -> Vec<Ball> {
         let prev_x = self.x;
+        self.prev_x = self.x;
         // Powerup timers
-        for timer in [&mut self.gun, &mut self.long, &mut self.balls_safe] {
+        for timer in [&mut self.gun, &mut self.long, &mut self.balls_safe, &mut self.short, &mut self.balls_fast, &mut self.reversed, &mut self.magnet, &mut self.fireball, &mut self.slow] {
             if let Some(t) = timer {
                 *t -= delta;
             }
@@ -145,75 +253,86 @@ impl Paddle {
 
         // Shooting
         self.shot_timer -= delta;
-        if (is_key_down(KeyCode::W) || is_key_down(KeyCode::Up)) && self.gun.is_some() && self.shot_timer <= 0.0 {
-            self.shot_timer = 0.3;
+        if input.fire && self.gun.is_some() && self.shot_timer <= 0.0 {
+            self.shot_timer = rules.gun_fire_delay;
             bullets.push(Bullet::new(vec2(self.x + 2.0, Paddle::y())));
             bullets.push(Bullet::new(vec2(self.x - 2.0 + self.width, Paddle::y())));
         }
 
         // Growing / shrinking
-        self.target_width = match self.long {
-            Some(_) => WIDTH_LONG,
-            _ => WIDTH_DEFAULT,
+        self.target_width = match (self.long, self.short) {
+            (Some(_), _) => rules.paddle_width_long,
+            (_, Some(_)) => rules.paddle_width_short,
+            _ => rules.paddle_width,
         };
         if self.width != self.target_width {
-            let change = if self.width > self.target_width { -1.0 } else { 1.0 } * delta * GROWTH_SPEED;
+            let change = if self.width > self.target_width { -1.0 } else { 1.0 } * delta * rules.paddle_growth_speed;
             self.width = (self.width + change).clamp(self.width.min(self.target_width), self.width.max(self.target_width));
             
             self.x -= change / 2.0;
             self.carry_x += prev_x - self.x;
+            for (_, x) in &mut self.stuck {
+                *x += prev_x - self.x;
+            }
         }
         
-        self.vel = 0.0;
-        if is_key_down(KeyCode::A) || is_key_down(KeyCode::Left) {
-            self.x -= delta * PADDLE_SPEED;
-            self.vel -= 1.0;
-        }
-        if is_key_down(KeyCode::D) || is_key_down(KeyCode::Right){
-            self.x += delta * PADDLE_SPEED;
-            self.vel += 1.0;
-        }
+        self.vel = input.move_axis.clamp(-1.0, 1.0) * if self.reversed.is_some() { -1.0 } else { 1.0 };
+        self.x += self.vel * delta * rules.paddle_speed;
 
         self.x = self.x.clamp(0.0, Level::view_size().x - self.width);
 
         if let Some(carry) = &mut self.carry {
             self.carry_x = self.carry_x.clamp(0.0, self.width - BALL_SIZE);
             carry.set_pos(vec2(self.x + self.carry_x, Paddle::y() - 4.0));
-            carry.set_vel(Vec2::from_angle(45.0_f32.to_radians()) * if self.vel == -1.0 { -1.0 } else { 1.0 })
+            carry.set_vel(Vec2::from_angle(45.0_f32.to_radians()) * if self.vel < 0.0 { -1.0 } else { 1.0 })
+        }
+        for (ball, x) in &mut self.stuck {
+            *x = x.clamp(0.0, self.width - BALL_SIZE);
+            ball.set_pos(vec2(self.x + *x, Paddle::y() - 4.0));
         }
 
-        if is_key_released(KEY_CARRY) {
-            return self.carry.take();
+        if input.carry_released {
+            return self.carry.take().into_iter().chain(self.stuck.drain(..).map(|(b, _)| b)).collect();
         }
-        None
+        vec![]
     }
 
-    pub fn draw(&self, texture: &Texture2D) {
-        let center_rect = self.center_rect();
+    #[cfg(feature = "graphics")]
+    pub fn draw(&self, texture: &Texture2D, alpha: f32) {
+        let x = self.prev_x + (self.x - self.prev_x) * alpha;
+        let center_rect = self.center_rect().offset(vec2(x - self.x, 0.0));
 
         let paddle_texture_offset = match self.gun {
             Some(t) if t > 2.0 || t % 0.2 >= 0.1 => 4.0,
             _ => 0.0,
         };
+        // Reddish while the controls are the wrong way round, flashing when they're about to go back
+        let col = match self.reversed {
+            Some(t) if t > 1.5 || t % 0.25 <= 0.125 => Color::new(1.0, 0.55, 0.55, 1.0),
+            _ => WHITE,
+        };
 
         // Sides
-        draw_texture_ex(texture, self.x, Paddle::y(), WHITE, DrawTextureParams {
+        draw_texture_ex(texture, x, Paddle::y(), col, DrawTextureParams {
             source: Some(PADDLE_LEFT_TEXTURE.offset(vec2(paddle_texture_offset, 0.0))),
             ..Default::default()
         });
-        draw_texture_ex(texture, self.x + center_rect.w + 1.0, Paddle::y(), WHITE, DrawTextureParams {
+        draw_texture_ex(texture, x + center_rect.w + 1.0, Paddle::y(), col, DrawTextureParams {
             source: Some(PADDLE_RIGHT_TEXTURE.offset(vec2(paddle_texture_offset, 0.0))),
             ..Default::default()
         });
         // Center
-        draw_texture_ex(texture, center_rect.x, Paddle::y(), WHITE, DrawTextureParams {
+        draw_texture_ex(texture, center_rect.x, Paddle::y(), col, DrawTextureParams {
             source: Some(PADDLE_CENTER_TEXTURE.offset(vec2(paddle_texture_offset, 0.0))),
             dest_size: Some(center_rect.size()),
             ..Default::default()
         });
 
         if let Some(carry) = &self.carry {
-            carry.draw(texture);
+            carry.draw_at(texture, vec2(x + self.carry_x, Paddle::y() - 4.0), self.fireball_display());
+        }
+        for (ball, stuck_x) in &self.stuck {
+            ball.draw_at(texture, vec2(x + stuck_x, Paddle::y() - 4.0), self.fireball_display());
         }
     }
 }
\ No newline at end of file
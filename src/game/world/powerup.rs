@@ -1,13 +1,107 @@
-use macroquad::{color::WHITE, math::{vec2, Rect, Vec2}, rand::gen_range, texture::{draw_texture_ex, DrawTextureParams, Texture2D}};
+use macroquad::math::{vec2, Rect, Vec2};
+#[cfg(feature = "graphics")]
+use macroquad::{color::WHITE, texture::{draw_texture_ex, DrawTextureParams, Texture2D}};
 
-use super::{level::Level, paddle::Paddle};
+use crate::game::bytes::{ByteReader, ByteWriter};
 
+use super::{level::Level, paddle::Paddle, rng::Rng};
+
+#[cfg(feature = "graphics")]
 const POWERUP_TEX_ORIGIN: Vec2 = vec2(15.0, 8.0); 
+// The bad ones didn't fit in the same row, and neither did anything after them
+#[cfg(feature = "graphics")]
+const NEGATIVE_POWERUP_TEX_ORIGIN: Vec2 = vec2(151.0, 31.0);
+#[cfg(feature = "graphics")]
+const MAGNET_TEX_POS: Vec2 = vec2(137.0, 31.0);
+#[cfg(feature = "graphics")]
+const FIREBALL_TEX_POS: Vec2 = vec2(118.0, 31.0);
+#[cfg(feature = "graphics")]
+const SLOW_TIME_TEX_POS: Vec2 = vec2(76.0, 31.0);
+#[cfg(feature = "graphics")]
+const EXTRA_LIFE_TEX_POS: Vec2 = vec2(90.0, 31.0);
+// Extra lives are rarer than everything else
+const EXTRA_LIFE_WEIGHT: f32 = 0.25;
 const POWERUP_SIZE: Vec2 = vec2(13.0, 7.0); 
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum PowerupKind {
-    PaddleCarry, PaddleGun, PaddleGrow, Zap, BallsFive, BallsTrail, BallsSafe
+    PaddleCarry, PaddleGun, PaddleGrow, Zap, BallsFive, BallsTrail, BallsSafe,
+    // These ones are bad, and should be dodged
+    PaddleShrink, BallsFast, ControlsReversed,
+    PaddleMagnet, BallsFire, SlowTime, ExtraLife,
+}
+
+impl TryFrom<u8> for PowerupKind {
+    type Error = ();
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(PowerupKind::PaddleCarry),
+            1 => Ok(PowerupKind::PaddleGun),
+            2 => Ok(PowerupKind::PaddleGrow),
+            3 => Ok(PowerupKind::Zap),
+            4 => Ok(PowerupKind::BallsFive),
+            5 => Ok(PowerupKind::BallsTrail),
+            6 => Ok(PowerupKind::BallsSafe),
+            7 => Ok(PowerupKind::PaddleShrink),
+            8 => Ok(PowerupKind::BallsFast),
+            9 => Ok(PowerupKind::ControlsReversed),
+            10 => Ok(PowerupKind::PaddleMagnet),
+            11 => Ok(PowerupKind::BallsFire),
+            12 => Ok(PowerupKind::SlowTime),
+            13 => Ok(PowerupKind::ExtraLife),
+            _ => Err(())
+        }
+    }
+}
+
+// A powerup a level has put under one of its tiles, which always drops when the tile breaks
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PlacedPowerup {
+    None,
+    // Picked the same way as any other powerup
+    Random,
+    Kind(PowerupKind),
+}
+
+// Saved as a byte, 0 for none, 1 for random, then every kind after that
+impl From<PlacedPowerup> for u8 {
+    fn from(value: PlacedPowerup) -> Self {
+        match value {
+            PlacedPowerup::None => 0,
+            PlacedPowerup::Random => 1,
+            PlacedPowerup::Kind(kind) => kind as u8 + 2,
+        }
+    }
+}
+impl TryFrom<u8> for PlacedPowerup {
+    type Error = ();
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(PlacedPowerup::None),
+            1 => Ok(PlacedPowerup::Random),
+            v => PowerupKind::try_from(v - 2).map(PlacedPowerup::Kind),
+        }
+    }
+}
+
+impl PowerupKind {
+    pub fn negative(&self) -> bool {
+        matches!(self, PowerupKind::PaddleShrink | PowerupKind::BallsFast | PowerupKind::ControlsReversed)
+    }
+
+    #[cfg(feature = "graphics")]
+    pub fn texture_rect(&self) -> Rect {
+        let (origin, index) = match (self, self.negative()) {
+            (PowerupKind::PaddleMagnet, _) => (MAGNET_TEX_POS, 0),
+            (PowerupKind::BallsFire, _)    => (FIREBALL_TEX_POS, 0),
+            (PowerupKind::SlowTime, _)     => (SLOW_TIME_TEX_POS, 0),
+            (PowerupKind::ExtraLife, _)    => (EXTRA_LIFE_TEX_POS, 0),
+            (_, true)  => (NEGATIVE_POWERUP_TEX_ORIGIN, *self as usize - PowerupKind::PaddleShrink as usize),
+            (_, false) => (POWERUP_TEX_ORIGIN, *self as usize),
+        };
+        let source_pos = origin + vec2(index as f32 * (POWERUP_SIZE.x + 1.0), 0.0);
+        Rect::new(source_pos.x, source_pos.y, POWERUP_SIZE.x, POWERUP_SIZE.y)
+    }
 }
 
 #[derive(PartialEq, Eq)]
@@ -17,36 +111,79 @@ pub enum PowerupHitState {
 
 pub struct Powerup {
     pos: Vec2,
+    prev_pos: Vec2,
     kind: PowerupKind,
     fall_speed: f32,
 }
 
 impl Powerup {
-    pub fn new(tile_index: usize, spawn_carry: bool) -> Self {
-        let random = gen_range(0, if spawn_carry {6} else {5});
+    // 'tile_pos' is where the tile it came out of is. Good powerups are all as likely as each other (apart from extra lives), and each bad one is 'negative_weight' as likely as a good one
+    pub fn new(tile_pos: Vec2, spawn_carry: bool, spawn_life: bool, negative_weight: f32, rng: &mut Rng) -> Self {
+        let mut kinds = vec![
+            (PowerupKind::PaddleGun,  1.0),
+            (PowerupKind::PaddleGrow, 1.0),
+            (PowerupKind::BallsFive,  1.0),
+            (PowerupKind::BallsTrail, 1.0),
+            (PowerupKind::BallsSafe,  1.0),
+            (PowerupKind::Zap,        1.0),
+            (PowerupKind::PaddleMagnet, 1.0),
+            (PowerupKind::BallsFire,  1.0),
+            (PowerupKind::SlowTime,   1.0),
+        ];
+        if spawn_carry {
+            kinds.push((PowerupKind::PaddleCarry, 1.0));
+        }
+        if spawn_life {
+            kinds.push((PowerupKind::ExtraLife, EXTRA_LIFE_WEIGHT));
+        }
+        kinds.extend([PowerupKind::PaddleShrink, PowerupKind::BallsFast, PowerupKind::ControlsReversed].map(|k| (k, negative_weight)));
+
+        let mut random = rng.gen_range(0.0, kinds.iter().map(|(_, w)| w).sum());
+        let kind = kinds
+            .iter()
+            .find(|(_, w)| { random -= w; random < 0.0 })
+            .map_or(PowerupKind::PaddleGun, |(k, _)| *k);
+        Self::with_kind(tile_pos, kind, rng)
+    }
+    pub fn with_kind(tile_pos: Vec2, kind: PowerupKind, rng: &mut Rng) -> Self {
+        let pos = tile_pos - 1.0;
         Self {
-            pos: Level::tile_pos(tile_index) - 1.0,
-            kind: match random {
-                0 => PowerupKind::PaddleGun,
-                1 => PowerupKind::PaddleGrow,
-                2 => PowerupKind::BallsFive,
-                3 => PowerupKind::BallsTrail,
-                4 => PowerupKind::BallsSafe,
-                _ => PowerupKind::PaddleCarry,
-            },
-            fall_speed: gen_range(25.0, 40.0),
+            pos,
+            prev_pos: pos,
+            kind,
+            fall_speed: rng.gen_range(25.0, 40.0),
         }
     }
 
     pub fn can_stop_game_over(&self) -> bool {
-        matches!(self.kind, PowerupKind::PaddleGun | PowerupKind::BallsFive)
+        // A zap might finish off the level
+        matches!(self.kind, PowerupKind::PaddleGun | PowerupKind::BallsFive | PowerupKind::Zap | PowerupKind::ExtraLife)
     }
 
     pub fn kind(&self) -> PowerupKind {
         self.kind
     }
+    pub fn pos(&self) -> Vec2 {
+        self.pos
+    }
+
+    pub fn write_snapshot(&self, w: &mut ByteWriter) {
+        w.vec2(self.pos);
+        w.vec2(self.prev_pos);
+        w.u8(self.kind as u8);
+        w.f32(self.fall_speed);
+    }
+    pub fn read_snapshot(r: &mut ByteReader) -> Option<Self> {
+        Some(Self {
+            pos: r.vec2()?,
+            prev_pos: r.vec2()?,
+            kind: PowerupKind::try_from(r.u8()?).ok()?,
+            fall_speed: r.f32()?,
+        })
+    }
 
     pub fn update(&mut self, delta: f32, paddle: &Paddle) -> PowerupHitState {
+        self.prev_pos = self.pos;
         self.pos.y += delta * self.fall_speed;
 
         let rect = Rect::new(self.pos.x, self.pos.y, POWERUP_SIZE.x, POWERUP_SIZE.y);
@@ -61,12 +198,11 @@ impl Powerup {
         }
     }
 
-    pub fn draw(&self, texture: &Texture2D) {
-        let source_pos = POWERUP_TEX_ORIGIN + vec2(self.kind as usize as f32 * (POWERUP_SIZE.x + 1.0), 0.0); 
-        let source = Rect::new(source_pos.x, source_pos.y, POWERUP_SIZE.x, POWERUP_SIZE.y);
-
-        draw_texture_ex(texture, self.pos.x, self.pos.y, WHITE, DrawTextureParams {
-            source: Some(source),
+    #[cfg(feature = "graphics")]
+    pub fn draw(&self, texture: &Texture2D, alpha: f32) {
+        let pos = self.prev_pos.lerp(self.pos, alpha);
+        draw_texture_ex(texture, pos.x, pos.y, WHITE, DrawTextureParams {
+            source: Some(self.kind.texture_rect()),
             ..Default::default()
         })
     }
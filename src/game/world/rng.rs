@@ -0,0 +1,83 @@
+use crate::game::bytes::{ByteReader, ByteWriter};
+
+// A small seedable random number generator (xorshift64*) that each world owns.
+// Nothing in the world touches macroquad's global rng, so a run can be reproduced exactly from its seed
+#[derive(Clone)]
+pub struct Rng {
+    seed: u64,
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // Mix the seed up (splitmix64) so similar seeds don't give similar sequences, and so the state is never 0
+        let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+
+        Self { seed, state: z.max(1) }
+    }
+
+    // A seed for something numbered 'n' that depends on 'seed', e.g. each level of a run
+    pub fn derive_seed(seed: u64, n: u64) -> u64 {
+        seed ^ n.wrapping_add(1).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn write_snapshot(&self, w: &mut ByteWriter) {
+        w.u64(self.seed);
+        w.u64(self.state);
+    }
+    pub fn read_snapshot(r: &mut ByteReader) -> Option<Self> {
+        let (seed, state) = (r.u64()?, r.u64()?);
+        // A state of 0 would only ever give 0s
+        (state != 0).then_some(Self { seed, state })
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    // A float from 0.0 (inclusive) to 1.0 (exclusive)
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    // Works like macroquad's 'gen_range', 'high' is exclusive
+    pub fn gen_range<T: RandomRange>(&mut self, low: T, high: T) -> T {
+        T::gen_range(self, low, high)
+    }
+
+    pub fn shuffle<T>(&mut self, slice: &mut [T]) {
+        for i in (1..slice.len()).rev() {
+            let j = self.gen_range(0, i + 1);
+            slice.swap(i, j);
+        }
+    }
+}
+
+pub trait RandomRange {
+    fn gen_range(rng: &mut Rng, low: Self, high: Self) -> Self;
+}
+
+impl RandomRange for usize {
+    fn gen_range(rng: &mut Rng, low: Self, high: Self) -> Self {
+        if high <= low {
+            return low;
+        }
+        low + (rng.next_u64() % (high - low) as u64) as usize
+    }
+}
+
+impl RandomRange for f32 {
+    fn gen_range(rng: &mut Rng, low: Self, high: Self) -> Self {
+        low + (high - low) * rng.next_f32()
+    }
+}
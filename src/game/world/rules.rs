@@ -0,0 +1,188 @@
+use crate::game::bytes::{ByteReader, ByteWriter};
+
+use super::{level::Tile, material::{default_materials, read_materials, write_materials, Material, MaterialTable}};
+
+// All of the numbers that decide how the game plays, so different rule sets (difficulties, packs, experiments) don't need recompiling.
+// The default matches how the game has always played
+#[derive(Clone, PartialEq, Debug)]
+pub struct GameRules {
+    // Balls
+    pub ball_speed: f32,
+    // Balls speed up a little every time they hit the paddle, between these multiples of 'ball_speed'
+    pub ball_min_speed: f32,
+    pub ball_max_speed: f32,
+    // How long without a ball on the paddle or a tile being hit before the ball's considered stuck
+    pub ball_stuck_time: f32,
+
+    // Paddle
+    pub paddle_speed: f32,
+    pub paddle_width: f32,
+    pub paddle_width_long: f32,
+    pub paddle_width_short: f32,
+    pub paddle_growth_speed: f32,
+    pub max_carries: usize,
+    pub gun_fire_delay: f32,
+
+    // Powerups
+    pub gun_duration: f32,
+    pub grow_duration: f32,
+    pub balls_safe_duration: f32,
+    pub magnet_duration: f32,
+    pub fireball_duration: f32,
+    pub slow_duration: f32,
+    // How fast balls and powerups go while time's slowed down
+    pub slow_speed: f32,
+    // How many of the closest tiles to the paddle a zap hits
+    pub zap_tiles: usize,
+    // The bad ones
+    pub shrink_duration: f32,
+    pub fast_duration: f32,
+    pub reverse_duration: f32,
+    // Fast balls go this much faster, on top of however fast they've sped up to
+    pub fast_ball_speed: f32,
+    // How likely each bad powerup is next to a good one (which are all 1.0). It starts at the first and goes up by the second every level, up to the third
+    pub negative_powerup_weight: (f32, f32, f32),
+    // How many tiles need breaking before the first powerup, as a range. Each tile counts as much as its material's drop weight
+    pub first_powerup_gap: (usize, usize),
+    // How many tiles need breaking between powerups, depending on how many balls there are.
+    // The first entry with at least as many balls as are in play is used, or the last one if there are more
+    pub powerup_gaps: Vec<(usize, (usize, usize))>,
+
+    // Tiles
+    pub materials: MaterialTable,
+    // How long after an explosive tile breaks before it hits the tiles around it
+    pub explosion_delay: f32,
+
+    // Lives and score
+    pub lives: usize,
+    // Extra lives stop dropping once there are this many
+    pub max_lives: usize,
+    pub powerup_score: usize,
+}
+
+impl Default for GameRules {
+    fn default() -> Self {
+        Self {
+            ball_speed: 70.0,
+            ball_min_speed: 1.0,
+            ball_max_speed: 1.3,
+            ball_stuck_time: 30.0,
+
+            paddle_speed: 100.0,
+            paddle_width: 20.0,
+            paddle_width_long: 40.0,
+            paddle_width_short: 12.0,
+            paddle_growth_speed: 40.0,
+            max_carries: 3,
+            gun_fire_delay: 0.3,
+
+            gun_duration: 7.0,
+            grow_duration: 15.0,
+            balls_safe_duration: 7.0,
+            magnet_duration: 12.0,
+            fireball_duration: 8.0,
+            slow_duration: 6.0,
+            slow_speed: 0.5,
+            zap_tiles: 5,
+            shrink_duration: 10.0,
+            fast_duration: 8.0,
+            reverse_duration: 6.0,
+            fast_ball_speed: 1.5,
+            negative_powerup_weight: (0.0, 0.05, 0.6),
+            first_powerup_gap: (0, 5),
+            powerup_gaps: vec![
+                (3,  (2,  5)),
+                (10, (4,  8)),
+                (15, (5, 10)),
+                (usize::MAX, (5, 15)),
+            ],
+
+            materials: default_materials(),
+            explosion_delay: 0.15,
+
+            lives: 2,
+            max_lives: 5,
+            powerup_score: 15,
+        }
+    }
+}
+
+impl GameRules {
+    // The range of how many tiles to break before the next powerup when there are 'balls' balls
+    pub fn powerup_gap(&self, balls: usize) -> (usize, usize) {
+        self.powerup_gaps
+            .iter()
+            .find(|(most, _)| balls <= *most)
+            .or(self.powerup_gaps.last())
+            .map(|(_, range)| *range)
+            .unwrap_or(self.first_powerup_gap)
+    }
+
+    // How likely each bad powerup is to drop on level 'level_number', counting from 0
+    pub fn negative_powerup_weight(&self, level_number: usize) -> f32 {
+        let (first, per_level, most) = self.negative_powerup_weight;
+        (first + per_level * level_number as f32).min(most).max(0.0)
+    }
+
+    pub fn material(&self, tile: Tile) -> &Material {
+        &self.materials[tile as usize]
+    }
+
+    pub fn write_snapshot(&self, w: &mut ByteWriter) {
+        for v in [
+            self.ball_speed, self.ball_min_speed, self.ball_max_speed, self.ball_stuck_time,
+            self.paddle_speed, self.paddle_width, self.paddle_width_long, self.paddle_growth_speed, self.gun_fire_delay,
+            self.gun_duration, self.grow_duration, self.balls_safe_duration,
+            self.explosion_delay,
+            self.paddle_width_short, self.shrink_duration, self.fast_duration, self.reverse_duration, self.fast_ball_speed,
+            self.negative_powerup_weight.0, self.negative_powerup_weight.1, self.negative_powerup_weight.2,
+            self.magnet_duration, self.fireball_duration, self.slow_duration, self.slow_speed,
+        ] {
+            w.f32(v);
+        }
+        for v in [self.max_carries, self.first_powerup_gap.0, self.first_powerup_gap.1, self.lives, self.powerup_score, self.zap_tiles, self.max_lives] {
+            w.usize(v);
+        }
+        w.list(&self.powerup_gaps, |w, (most, (min, max))| {
+            w.usize(*most);
+            w.usize(*min);
+            w.usize(*max);
+        });
+        write_materials(&self.materials, w);
+    }
+    pub fn read_snapshot(r: &mut ByteReader) -> Option<Self> {
+        Some(Self {
+            ball_speed: r.f32()?,
+            ball_min_speed: r.f32()?,
+            ball_max_speed: r.f32()?,
+            ball_stuck_time: r.f32()?,
+            paddle_speed: r.f32()?,
+            paddle_width: r.f32()?,
+            paddle_width_long: r.f32()?,
+            paddle_growth_speed: r.f32()?,
+            gun_fire_delay: r.f32()?,
+            gun_duration: r.f32()?,
+            grow_duration: r.f32()?,
+            balls_safe_duration: r.f32()?,
+            explosion_delay: r.f32()?,
+            paddle_width_short: r.f32()?,
+            shrink_duration: r.f32()?,
+            fast_duration: r.f32()?,
+            reverse_duration: r.f32()?,
+            fast_ball_speed: r.f32()?,
+            negative_powerup_weight: (r.f32()?, r.f32()?, r.f32()?),
+            magnet_duration: r.f32()?,
+            fireball_duration: r.f32()?,
+            slow_duration: r.f32()?,
+            slow_speed: r.f32()?,
+            max_carries: r.usize()?,
+            first_powerup_gap: (r.usize()?, r.usize()?),
+            lives: r.usize()?,
+            powerup_score: r.usize()?,
+            zap_tiles: r.usize()?,
+            max_lives: r.usize()?,
+            powerup_gaps: r.list(|r| Some((r.usize()?, (r.usize()?, r.usize()?))))?,
+            materials: read_materials(r)?,
+        })
+    }
+}
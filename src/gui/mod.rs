@@ -74,12 +74,18 @@ impl Button {
 
 pub struct TextField {
     rect: Rect,
+    len: usize,
 }
 
 impl TextField {
     pub fn new(pos: Vec2) -> Self {
+        Self::with_len(pos, LEVEL_NAME_LEN)
+    }
+    // Wide enough for 'len' characters, and won't take any more than that
+    pub fn with_len(pos: Vec2, len: usize) -> Self {
         Self {
-            rect: Rect::new(pos.x, pos.y, 97.0, 7.0),
+            rect: Rect::new(pos.x, pos.y, len as f32 * 6.0 + 1.0, 7.0),
+            len,
         }
     }
 
@@ -89,7 +95,7 @@ impl TextField {
     pub fn update(&mut self, text: &mut String) -> bool {
         let mut already_backspaced = false;
         let backspace = |text: &mut String, already_backspaced: &mut bool| {
-            if text.len() > 0 && !*already_backspaced {
+            if !text.is_empty() && !*already_backspaced {
                 let new_len = text.len() - 1;
                 text.truncate(new_len);
                 *already_backspaced = true;
@@ -98,10 +104,10 @@ impl TextField {
 
         if let Some(c) = get_char_pressed() {
             let c = c.to_ascii_uppercase();
-            if char_valid(c) && text.len() < LEVEL_NAME_LEN {
+            if char_valid(c) && text.len() < self.len {
                 text.push(c);
             }
-            if c == '\u{8}' && text.len() > 0 {
+            if c == '\u{8}' && !text.is_empty() {
                 backspace(text, &mut already_backspaced);
             }
             if c == '\r' {
@@ -113,12 +119,12 @@ impl TextField {
             backspace(text, &mut already_backspaced);
         }
         
-        return false;
+        false
     }
 
-    pub fn draw(&self, texture: &Texture2D, text: &String, name: &String, flash: bool, flash_col: Color, grey_col: Color) {
+    pub fn draw(&self, texture: &Texture2D, text: &str, name: &str, flash: bool, flash_col: Color, grey_col: Color) {
         let rect = self.rect;
-        let underscore_amount = LEVEL_NAME_LEN.saturating_sub(text.len());
+        let underscore_amount = self.len.saturating_sub(text.len());
         if flash {
             draw_rectangle(rect.x, rect.y, rect.w, rect.h, flash_col);
         }
@@ -159,7 +165,7 @@ impl Gui {
                 continue;
             }
 
-            if button.rect.contains(mouse_pos) && self.hot_item == None {
+            if button.rect.contains(mouse_pos) && self.hot_item.is_none() {
                 button.state = ButtonState::Hovered;
                 self.hot_item = Some(*id);
             }
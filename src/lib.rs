@@ -0,0 +1,34 @@
+// The simulation (game::world, level packs) only needs macroquad's maths types, so it builds and runs without a window or a gpu.
+// Everything that draws, reads input or is a scene sits behind the 'graphics' feature (on by default).
+// e.g. 'cargo run --example simulate --no-default-features'
+
+pub mod game;
+pub mod text_renderer;
+#[cfg(feature = "graphics")]
+pub mod editor;
+#[cfg(feature = "graphics")]
+pub mod gui;
+#[cfg(feature = "graphics")]
+pub mod main_menu;
+
+#[cfg(feature = "graphics")]
+use game::level_pack::PackLoadError;
+#[cfg(feature = "graphics")]
+use macroquad::{math::Vec2, texture::Texture2D};
+
+#[cfg(feature = "graphics")]
+pub enum SceneChange {
+    MainMenu,
+    Game,
+    // Carry on the saved session, if it was playing the loaded pack
+    ResumeGame,
+    Editor { new: bool },
+    Replay,
+}
+#[cfg(feature = "graphics")]
+pub trait Scene {
+    fn update(&mut self, mouse_pos: Vec2) -> Option<SceneChange>;
+    fn draw(&self, texture: &Texture2D, level_pack_info: Option<(&String, &String)>);
+    // Called when a pack was picked but couldn't be loaded, so the scene can say why
+    fn pack_load_failed(&mut self, _error: &PackLoadError) {}
+}
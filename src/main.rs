@@ -1,27 +1,9 @@
-use editor::Editor;
-use game::{level_pack::LevelPack, world::level::Level, Game};
+use breakout::{editor::Editor, game::{level_pack::LevelPack, replay::Replay, replay_player::ReplayPlayer, world::level::Level, Game}, main_menu::MainMenu, Scene, SceneChange};
 use macroquad::prelude::*;
-use main_menu::MainMenu;
 // use sapp_jsutils::JsObject;
 
-pub mod game;
-pub mod editor;
-pub mod gui;
-pub mod text_renderer;
-pub mod main_menu;
-
 #[cfg(target_arch = "wasm32")]
-use game::level_pack::{set_try_flag, try_load_level};
-
-pub enum SceneChange {
-    MainMenu,
-    Game,
-    Editor { new: bool },
-}
-pub trait Scene {
-    fn update(&mut self, mouse_pos: Vec2) -> Option<SceneChange>;
-    fn draw(&self, texture: &Texture2D, level_pack_info: Option<(&String, &String)>);
-}
+use breakout::game::level_pack::{set_try_flag, try_load_level};
 
 fn window_conf()-> Conf {
     let window_size = Level::view_size();
@@ -45,7 +27,7 @@ async fn main() {
 
     let camera = Camera2D::from_display_rect(Rect::new(0.0, view_size.y, view_size.x, -view_size.y));
 
-    #[cfg(not(target_arch = "wasm"))]
+    #[cfg(not(target_arch = "wasm32"))]
     let level_pack: Option<LevelPack> = Some(LevelPack::load_from_file(include_bytes!("../ALBUMS I LIKE.brk").into()).unwrap());
     #[cfg(target_arch = "wasm32")]
     let mut level_pack: Option<LevelPack> = None;
@@ -54,8 +36,13 @@ async fn main() {
 
     loop {
         #[cfg(target_arch = "wasm32")]
-        if let Some(lp) = try_load_level() {
-            level_pack = Some(lp);
+        match try_load_level() {
+            Some(Ok(lp)) => level_pack = Some(lp),
+            Some(Err(e)) => {
+                macroquad::logging::warn!("couldn't load level pack: {}", e);
+                scene.pack_load_failed(&e);
+            }
+            None => (),
         }
 
         set_camera(&camera);
@@ -68,7 +55,15 @@ async fn main() {
             scene = match (change, &level_pack) {
                 (SceneChange::Editor { new: false }, Some(lp)) => Box::new(Editor::from_level_pack(lp.clone())),
                 (SceneChange::Editor { new: true }, _)         => Box::new(Editor::default()),
-                (SceneChange::Game, Some(lp)) => Box::new(Game::new(lp.clone())),
+                (SceneChange::Game, Some(lp)) => Box::new(Game::new(lp.clone(), macroquad::rand::rand() as u64)),
+                (SceneChange::ResumeGame, Some(lp)) => match Game::resume_session(lp.clone()) {
+                    Some(game) => Box::new(game),
+                    None => scene,
+                },
+                (SceneChange::Replay, Some(lp)) => match Replay::load(lp.name()) {
+                    Some(replay) => Box::new(ReplayPlayer::new(lp.clone(), replay)),
+                    None => scene,
+                },
                 (SceneChange::MainMenu, _) =>Box::new(MainMenu::new()),
                 _ => scene
             };
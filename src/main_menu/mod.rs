@@ -2,29 +2,48 @@ use std::collections::HashMap;
 
 use macroquad::{color::{Color, BLUE, GREEN, ORANGE, PURPLE, RED, WHITE, YELLOW}, math::{vec2, Rect, Vec2}, shapes::{draw_rectangle, draw_rectangle_lines}, texture::{draw_texture_ex, DrawTextureParams, Texture2D}, window::clear_background};
 
-use crate::{game::world::BG_COL, gui::{Button, ButtonDetail, Gui, Id, BUTTON_COL_HOVER, BUTTON_COL_IDLE, BUTTON_DETAIL_GREY, GRID_COL}, text_renderer::{render_text, TextAlign}, Scene, SceneChange};
+use crate::{game::{level_pack::PackLoadError, session::saved_session_pack, world::{level::Level, BG_COL}}, gui::{Button, ButtonDetail, Gui, Id, BUTTON_COL_HOVER, BUTTON_COL_IDLE, BUTTON_DETAIL_GREY, GRID_COL}, text_renderer::{render_text, wrap_text, TextAlign}, Scene, SceneChange};
 
 pub struct MainMenu {
     gui: Gui,
     name_timer: f32,
     on_info_tab: bool,
     info_flash: f32,
+    // The pack the saved session is for, the continue button only works if it's the one loaded
+    saved_session_pack: Option<String>,
+    // Why the last pack couldn't be loaded, and how much longer to show it for
+    pack_error: Option<(String, f32)>,
+}
+
+const PACK_ERROR_TIME: f32 = 8.0;
+const PACK_ERROR_TITLE: &str = "COULDN'T LOAD:";
+// As many characters as fit across the screen
+const PACK_ERROR_LINE_LEN: usize = 32;
+
+impl Default for MainMenu {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl MainMenu {
     pub fn new() -> Self {
         let mut buttons = HashMap::new();
-        buttons.insert(0, Button::new(Rect::new(24.0, 110.0, 144.0, 10.0), ButtonDetail::Text(String::from("         PLAY         ")), vec2(6.0, 2.0)));
+        buttons.insert(0, Button::new(Rect::new(24.0, 110.0, 70.0, 10.0), ButtonDetail::Text(String::from("PLAY")), vec2(24.0, 2.0)));
+        buttons.insert(6, Button::new(Rect::new(98.0, 110.0, 70.0, 10.0), ButtonDetail::Text(String::from("CONTINUE")), vec2(12.0, 2.0)));
         buttons.insert(1, Button::new(Rect::new(24.0, 130.0, 144.0, 10.0), ButtonDetail::Text(String::from("EDIT CURRENT LEVEL PACK")), vec2(3.0, 2.0)));
         buttons.insert(2, Button::new(Rect::new(24.0, 150.0, 144.0, 10.0), ButtonDetail::Text(String::from("  EDIT NEW LEVEL PACK  ")), vec2(3.0, 2.0)));
         buttons.insert(3, Button::new(Rect::new(24.0, 170.0, 144.0, 10.0), ButtonDetail::Text(String::from("         INFO         ")), vec2(6.0, 2.0)));
         buttons.insert(4, Button::new(Rect::new(24.0, 170.0, 144.0, 10.0), ButtonDetail::Text(String::from("         BACK         ")), vec2(6.0, 2.0)));
+        buttons.insert(5, Button::new(Rect::new(24.0, 190.0, 144.0, 10.0), ButtonDetail::Text(String::from("     WATCH REPLAY     ")), vec2(6.0, 2.0)));
 
         Self {
             gui: Gui::new(buttons),
             name_timer: 0.0,
             on_info_tab: false,
             info_flash: 0.0,
+            saved_session_pack: saved_session_pack(),
+            pack_error: None,
         }
     }
 }
@@ -32,7 +51,7 @@ impl MainMenu {
 impl Scene for MainMenu {
     fn update(&mut self, mouse_pos: Vec2) -> Option<SceneChange> {
         let update_only: Option<&[Id]> = match self.on_info_tab {
-            false => Some(&[0, 1, 2, 3]),
+            false => Some(&[0, 1, 2, 3, 5, 6]),
             true => Some(&[4]),
         };
         self.gui.update(mouse_pos, update_only);
@@ -41,6 +60,8 @@ impl Scene for MainMenu {
             (0, SceneChange::Game),
             (1, SceneChange::Editor { new: false }),
             (2, SceneChange::Editor { new: true }),
+            (5, SceneChange::Replay),
+            (6, SceneChange::ResumeGame),
         ] {
             if self.gui.button(id).is_some_and(|b| b.released()) {
                 return Some(scene_change);
@@ -49,6 +70,10 @@ impl Scene for MainMenu {
 
         self.info_flash = (self.info_flash + macroquad::time::get_frame_time()) % 1.0;
         self.name_timer += macroquad::time::get_frame_time();
+        if let Some((_, t)) = &mut self.pack_error {
+            *t -= macroquad::time::get_frame_time();
+        }
+        self.pack_error = self.pack_error.take().filter(|(_, t)| *t > 0.0);
 
         if self.gui.button(3).is_some_and(|b| b.released()) {
             self.info_flash = 0.0;
@@ -61,6 +86,10 @@ impl Scene for MainMenu {
         None
     }
 
+    fn pack_load_failed(&mut self, error: &PackLoadError) {
+        self.pack_error = Some((error.to_string().to_uppercase(), PACK_ERROR_TIME));
+    }
+
     fn draw(&self, texture: &Texture2D, level_pack_info: Option<(&String, &String)>) {
         clear_background(BG_COL);
 
@@ -85,7 +114,7 @@ impl Scene for MainMenu {
 
                 if wasd {
                     for ((x, y), s) in origins.iter().zip(["A", "S", "W", "D"]) {
-                        render_text(&s.to_string(), vec2(*x, *y) + 2.0, WHITE, TextAlign::Left, texture);
+                        render_text(s, vec2(*x, *y) + 2.0, WHITE, TextAlign::Left, texture);
 
                     }
                 } else {
@@ -160,12 +189,25 @@ impl Scene for MainMenu {
             } else {
                 render_text(&String::from(" NO PACK LOADED! "), vec2(47.0, 81.0), BUTTON_DETAIL_GREY, TextAlign::Left, texture);
             }
+
+            // There's only room for two lines above the buttons, so the message starts on the same line as the title
+            if let Some((message, _)) = &self.pack_error {
+                let center = Level::view_size().x / 2.0;
+                for (i, line) in wrap_text(&format!("{} {}", PACK_ERROR_TITLE, message), PACK_ERROR_LINE_LEN).iter().enumerate() {
+                    let pos = vec2(center, 95.0 + i as f32 * 7.0);
+                    render_text(line, pos, BUTTON_DETAIL_GREY, TextAlign::Center, texture);
+                    if i == 0 {
+                        // Padded out to the line's length so it lines up with it
+                        render_text(&format!("{:<1$}", PACK_ERROR_TITLE, line.len()), pos, RED, TextAlign::Center, texture);
+                    }
+                }
+            }
         }
         
 
         // Buttons
         let ids: &[Id] = match self.on_info_tab {
-            false => &[0, 1, 2, 3],
+            false => &[0, 1, 2, 3, 5, 6],
             true => &[4],
         };
         for &id in ids {
@@ -173,7 +215,13 @@ impl Scene for MainMenu {
                 Some(b) => b,
                 None => continue,
             };
-            let gray = (id == 0 || id == 1) && level_pack_info.is_none();
+            let gray = match id {
+                0 | 1 => level_pack_info.is_none(),
+                // Replays aren't saved in the browser, so there's never one to watch
+                5 => level_pack_info.is_none() || cfg!(target_arch = "wasm32"),
+                6 => level_pack_info.is_none_or(|(name, _)| self.saved_session_pack.as_ref() != Some(name)),
+                _ => false,
+            };
             button.draw(texture, if gray { BUTTON_DETAIL_GREY } else { WHITE }, if button.idle() || gray { BUTTON_COL_IDLE } else { BUTTON_COL_HOVER }, GRID_COL);
         }
     }
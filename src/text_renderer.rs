@@ -1,18 +1,27 @@
+#[cfg(feature = "graphics")]
 use macroquad::{color::Color, math::{vec2, Rect, Vec2}, texture::{draw_texture_ex, DrawTextureParams, Texture2D}};
 
+#[cfg(feature = "graphics")]
 const CHAR_WIDTH: f32 = 5.0;
+#[cfg(feature = "graphics")]
 const CHAR_HEIGHT: f32 = 6.0;
+#[cfg(feature = "graphics")]
 const CHARS_ORIGIN: Vec2 = vec2(1.0, 16.0);
 
 #[derive(PartialEq, Eq, Clone, Copy)]
 pub enum TextAlign {
-    Left, Right
+    Left, Right, Center
 }
 
-pub fn render_text(text: &String, pos: Vec2, color: Color, align: TextAlign, texture: &Texture2D) {
+#[cfg(feature = "graphics")]
+pub fn render_text(text: &str, pos: Vec2, color: Color, align: TextAlign, texture: &Texture2D) {
     let (change, iter): (f32, Box<dyn Iterator<Item=_>>) = match align {
         TextAlign::Left  => ( CHAR_WIDTH + 1.0, Box::new(text.chars())),
         TextAlign::Right => (-CHAR_WIDTH - 1.0, Box::new(text.chars().rev())),
+        TextAlign::Center => {
+            let width = text.chars().count() as f32 * (CHAR_WIDTH + 1.0) - 1.0;
+            return render_text(text, pos - vec2((width / 2.0).floor(), 0.0), color, TextAlign::Left, texture);
+        }
     };
 
     let mut x = 0.0;
@@ -24,8 +33,8 @@ pub fn render_text(text: &String, pos: Vec2, color: Color, align: TextAlign, tex
     for c in iter {
         let c_pos = match c {
             ' ' => { x += change; continue; }
-            'A'..='Z' => (c as u8 - 'A' as u8, 1),
-            '0'..='9' => (c as u8 - '0' as u8, 0),
+            'A'..='Z' => (c as u8 - b'A', 1),
+            '0'..='9' => (c as u8 - b'0', 0),
             '_' => (10, 0),
             '?' => (11, 0),
             '!' => (12, 0),
@@ -58,6 +67,41 @@ pub fn render_text(text: &String, pos: Vec2, color: Color, align: TextAlign, tex
     }
 }
 
+// Splits text into lines of at most 'max_chars', breaking between words where it can
+#[cfg(feature = "graphics")]
+pub fn wrap_text(text: &str, max_chars: usize) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for word in text.split_whitespace() {
+        let mut word: Vec<char> = word.chars().collect();
+        match lines.last_mut() {
+            Some(line) if line.chars().count() + 1 + word.len() <= max_chars => {
+                line.push(' ');
+                line.extend(word);
+                continue;
+            }
+            _ => {}
+        }
+        // Words too long for a line of their own are cut up
+        while word.len() > max_chars {
+            lines.push(word.drain(..max_chars).collect());
+        }
+        lines.push(word.into_iter().collect());
+    }
+    lines
+}
+
 pub fn char_valid(c: char) -> bool {
     c.is_ascii_uppercase() || c.is_ascii_digit() || [' ', '_', '?', '!', '*', ':', '-', '+', '/', '(', ')', '<', '>', '.', ',', '\''].contains(&c)
-}
\ No newline at end of file
+}
+#[cfg(all(test, feature = "graphics"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wraps_between_words() {
+        assert_eq!(wrap_text("COULDN'T LOAD: FILE ENDS EARLY AT BYTE 1234", 20), vec!["COULDN'T LOAD: FILE", "ENDS EARLY AT BYTE", "1234"]);
+        assert_eq!(wrap_text("SHORT", 20), vec!["SHORT"]);
+        assert_eq!(wrap_text("ABCDEFGHIJ KL", 4), vec!["ABCD", "EFGH", "IJ", "KL"]);
+        assert!(wrap_text("", 20).is_empty());
+    }
+}